@@ -0,0 +1,21 @@
+//! Subtree summaries kept up to date by the Red and Black Binary Tree
+
+/// Summary computed for every subtree of an augmented tree. `combine` must be
+/// associative so that the result does not depend on the shape of the tree
+pub trait Augment<T> {
+    /// Summary of a subtree
+    type Value: Clone;
+    /// Summary of an empty subtree
+    fn identity() -> Self::Value;
+    /// Summary of a node from its left subtree, its content and its right subtree
+    fn combine(left: &Self::Value, content: &T, right: &Self::Value) -> Self::Value;
+}
+
+/// No augmentation, the summary is zero sized and never computed
+impl<T> Augment<T> for () {
+    type Value = ();
+    #[inline(always)]
+    fn identity() -> Self::Value {}
+    #[inline(always)]
+    fn combine(_left: &Self::Value, _content: &T, _right: &Self::Value) -> Self::Value {}
+}
@@ -0,0 +1,249 @@
+//! Cursors over the Red and Black Binary Tree
+use super::{next_index, prev_index, Allocator, Augment, Global, RBBTree, NO_ENTRY};
+use core::borrow::Borrow;
+use core::ops::Bound;
+
+/// Cursor pointing to an element of a Binary Tree, or to the ghost position
+/// located between the last and the first element
+pub struct Cursor<'a, T: PartialOrd, A: Allocator = Global, G: Augment<T> = ()> {
+    tree: &'a RBBTree<T, A, G>,
+    current: usize,
+}
+
+/// Cursor able to edit the Binary Tree around the element it points to
+pub struct CursorMut<'a, T: PartialOrd, A: Allocator = Global, G: Augment<T> = ()> {
+    tree: &'a mut RBBTree<T, A, G>,
+    current: usize,
+}
+
+impl<T: PartialOrd, A: Allocator, G: Augment<T>> RBBTree<T, A, G> {
+    /// Create a cursor pointing to the smallest element
+    pub fn cursor_front(&self) -> Cursor<'_, T, A, G> {
+        Cursor {
+            current: self.first_index(),
+            tree: self,
+        }
+    }
+    /// Create a cursor pointing to the greatest element
+    pub fn cursor_back(&self) -> Cursor<'_, T, A, G> {
+        Cursor {
+            current: self.last_index(),
+            tree: self,
+        }
+    }
+    /// Create a cursor pointing to the first element not located before the bound
+    pub fn lower_bound<Q>(&self, bound: Bound<&Q>) -> Cursor<'_, T, A, G>
+    where
+        T: Borrow<Q>,
+        Q: PartialOrd + ?Sized,
+    {
+        Cursor {
+            current: self.lower_bound_index(bound),
+            tree: self,
+        }
+    }
+    /// Create a mutable cursor pointing to the smallest element
+    pub fn cursor_front_mut(&mut self) -> CursorMut<'_, T, A, G> {
+        CursorMut {
+            current: self.first_index(),
+            tree: self,
+        }
+    }
+    /// Create a mutable cursor pointing to the greatest element
+    pub fn cursor_back_mut(&mut self) -> CursorMut<'_, T, A, G> {
+        CursorMut {
+            current: self.last_index(),
+            tree: self,
+        }
+    }
+    /// Create a mutable cursor pointing to the first element not located before the bound
+    pub fn lower_bound_mut<Q>(&mut self, bound: Bound<&Q>) -> CursorMut<'_, T, A, G>
+    where
+        T: Borrow<Q>,
+        Q: PartialOrd + ?Sized,
+    {
+        CursorMut {
+            current: self.lower_bound_index(bound),
+            tree: self,
+        }
+    }
+    /// Next position of a cursor, the ghost position is followed by the smallest element
+    fn cursor_next(&self, x: usize) -> usize {
+        if x == NO_ENTRY {
+            self.first_index()
+        } else {
+            next_index(&self.data, x)
+        }
+    }
+    /// Previous position of a cursor, the ghost position is preceded by the greatest element
+    fn cursor_prev(&self, x: usize) -> usize {
+        if x == NO_ENTRY {
+            self.last_index()
+        } else {
+            prev_index(&self.data, x)
+        }
+    }
+    fn content_at(&self, x: usize) -> Option<&T> {
+        (x != NO_ENTRY).then(|| self.data[x].content())
+    }
+}
+
+impl<'a, T: PartialOrd, A: Allocator, G: Augment<T>> Cursor<'a, T, A, G> {
+    /// Get the element pointed by the cursor, `None` on the ghost position
+    pub fn current(&self) -> Option<&'a T> {
+        self.tree.content_at(self.current)
+    }
+    /// Move the cursor to the next element
+    pub fn move_next(&mut self) {
+        self.current = self.tree.cursor_next(self.current);
+    }
+    /// Move the cursor to the previous element
+    pub fn move_prev(&mut self) {
+        self.current = self.tree.cursor_prev(self.current);
+    }
+    /// Get the next element without moving the cursor
+    pub fn peek_next(&self) -> Option<&'a T> {
+        self.tree.content_at(self.tree.cursor_next(self.current))
+    }
+    /// Get the previous element without moving the cursor
+    pub fn peek_prev(&self) -> Option<&'a T> {
+        self.tree.content_at(self.tree.cursor_prev(self.current))
+    }
+}
+
+impl<'a, T: PartialOrd, A: Allocator, G: Augment<T>> CursorMut<'a, T, A, G> {
+    /// Get the element pointed by the cursor, `None` on the ghost position
+    pub fn current(&self) -> Option<&T> {
+        self.tree.content_at(self.current)
+    }
+    /// Move the cursor to the next element
+    pub fn move_next(&mut self) {
+        self.current = self.tree.cursor_next(self.current);
+    }
+    /// Move the cursor to the previous element
+    pub fn move_prev(&mut self) {
+        self.current = self.tree.cursor_prev(self.current);
+    }
+    /// Get the next element without moving the cursor
+    pub fn peek_next(&self) -> Option<&T> {
+        self.tree.content_at(self.tree.cursor_next(self.current))
+    }
+    /// Get the previous element without moving the cursor
+    pub fn peek_prev(&self) -> Option<&T> {
+        self.tree.content_at(self.tree.cursor_prev(self.current))
+    }
+    /// Get a read only cursor at the same position
+    pub fn as_cursor(&self) -> Cursor<'_, T, A, G> {
+        Cursor {
+            tree: self.tree,
+            current: self.current,
+        }
+    }
+    /// Remove the element pointed by the cursor and move to the next one
+    pub fn remove_current(&mut self) -> Option<T> {
+        if self.current == NO_ENTRY {
+            return None;
+        }
+        let next = next_index(&self.tree.data, self.current);
+        let content = self.tree.remove_index(self.current);
+        // swap_remove moved the last node of the arena into the freed slot, a
+        // free-list arena moves nothing
+        if next != self.tree.data.len() {
+            self.current = next;
+        }
+        Some(content)
+    }
+    /// Insert an element right after the cursor, or at the front on the ghost
+    /// position. The element is given back if it breaks the order
+    pub fn insert_after(&mut self, content: T) -> Result<(), T> {
+        let next = self.tree.cursor_next(self.current);
+        if matches!(self.current(), Some(current) if content < *current)
+            || matches!(self.tree.content_at(next), Some(next) if *next < content)
+        {
+            return Err(content);
+        }
+        if self.current != NO_ENTRY && self.tree.data[self.current].right() == NO_ENTRY {
+            self.tree.insert_at(self.current, false, content);
+        } else {
+            // Empty left slot of the next element, or root of an empty tree
+            self.tree.insert_at(next, true, content);
+        }
+        Ok(())
+    }
+    /// Insert an element right before the cursor, or at the back on the ghost
+    /// position. The element is given back if it breaks the order
+    pub fn insert_before(&mut self, content: T) -> Result<(), T> {
+        let prev = self.tree.cursor_prev(self.current);
+        if matches!(self.current(), Some(current) if *current < content)
+            || matches!(self.tree.content_at(prev), Some(prev) if content < *prev)
+        {
+            return Err(content);
+        }
+        if self.current != NO_ENTRY && self.tree.data[self.current].left() == NO_ENTRY {
+            self.tree.insert_at(self.current, true, content);
+        } else {
+            // Empty right slot of the previous element, or root of an empty tree
+            self.tree.insert_at(prev, false, content);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::RBBTree;
+    use std::ops::Bound;
+    #[test]
+    fn navigation() {
+        let mut rnb = RBBTree::new();
+        for val in [4, 1, 3, 0, 2] {
+            rnb.insert(val);
+        }
+        let mut cursor = rnb.lower_bound(Bound::Excluded(&2));
+        assert_eq!(cursor.current(), Some(&3));
+        assert_eq!(cursor.peek_prev(), Some(&2));
+        cursor.move_next();
+        cursor.move_next();
+        assert_eq!(cursor.current(), None);
+        assert_eq!(cursor.peek_next(), Some(&0));
+        cursor.move_prev();
+        assert_eq!(cursor.current(), Some(&4));
+        assert_eq!(rnb.cursor_back().current(), Some(&4));
+        assert_eq!(rnb.lower_bound(Bound::Included(&5)).current(), None);
+    }
+    #[test]
+    fn edition() {
+        let mut rnb = RBBTree::new();
+        let mut cursor = rnb.cursor_front_mut();
+        assert_eq!(cursor.insert_after(10), Ok(()));
+        assert_eq!(cursor.current(), None);
+        cursor.move_next();
+        for val in (0..10).rev() {
+            assert_eq!(cursor.insert_before(val), Ok(()));
+            cursor.move_prev();
+        }
+        assert_eq!(cursor.insert_before(1), Err(1));
+        assert_eq!(cursor.insert_after(-1), Err(-1));
+        for val in 0..10 {
+            assert_eq!(cursor.current(), Some(&val));
+            assert_eq!(cursor.insert_after(val), Ok(()));
+            cursor.move_next();
+            cursor.move_next();
+        }
+        rnb.check_nodes();
+        assert!(rnb
+            .iter()
+            .copied()
+            .eq((0..10).flat_map(|x| [x, x]).chain([10])));
+        let mut cursor = rnb.cursor_front_mut();
+        while let Some(val) = cursor.current().copied() {
+            if val % 2 == 1 || val == 10 {
+                assert_eq!(cursor.remove_current(), Some(val));
+            } else {
+                cursor.move_next();
+            }
+        }
+        rnb.check_nodes();
+        assert!(rnb.iter().copied().eq([0, 0, 2, 2, 4, 4, 6, 6, 8, 8]));
+    }
+}
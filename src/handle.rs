@@ -0,0 +1,171 @@
+//! Stable handles to the elements of a Red and Black Binary Tree
+//!
+//! Arena indexes move when `swap_remove` fills a freed slot with the last node,
+//! or when `compact` packs a free-list arena, so handles go through an
+//! indirection table kept up to date on every move.
+//! Every slot carries a generation, bumped when its element leaves the tree, so
+//! a stale handle is detected instead of pointing to another element. The table
+//! is only allocated once a first handle is requested.
+//!
+//! Handles survive insertions, removals, cursor edition and `extend`. Operations
+//! rebuilding the arena (`split_off`, `append`, `join` and `drain`) invalidate
+//! all of them
+use super::{Allocator, Augment, RBBTree, NO_ENTRY};
+use alloc::vec::Vec;
+
+/// Stable reference to an element of a Binary Tree, given by `insert_with_handle`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Handle {
+    slot: usize,
+    generation: u32,
+}
+
+struct Slot {
+    /// Arena index of the element, `NO_ENTRY` once it is removed
+    index: usize,
+    generation: u32,
+}
+
+/// Indirection table between handles and arena indexes
+#[derive(Default)]
+pub(super) struct HandleTable {
+    slots: Vec<Slot>,
+    free: Vec<usize>,
+    /// Slot of every node of the arena, `NO_ENTRY` for the nodes without handle.
+    /// The nodes pushed since the last update are missing at the end
+    slot_of: Vec<usize>,
+}
+
+impl HandleTable {
+    /// Give a handle to the node at `index`
+    fn attach(&mut self, index: usize, len: usize) -> Handle {
+        self.slot_of.resize(len, NO_ENTRY);
+        let slot = match self.free.pop() {
+            Some(slot) => {
+                self.slots[slot].index = index;
+                slot
+            }
+            None => {
+                self.slots.push(Slot {
+                    index,
+                    generation: 0,
+                });
+                self.slots.len() - 1
+            }
+        };
+        self.slot_of[index] = slot;
+        Handle {
+            slot,
+            generation: self.slots[slot].generation,
+        }
+    }
+    /// Arena index of a live handle
+    fn resolve(&self, handle: Handle) -> Option<usize> {
+        match self.slots.get(handle.slot) {
+            Some(slot) if slot.generation == handle.generation && slot.index != NO_ENTRY => {
+                Some(slot.index)
+            }
+            _ => None,
+        }
+    }
+    /// Follow `swap_remove`: the node at `index` is dropped and the last one of
+    /// the `len` nodes takes its place
+    pub(super) fn swap_remove(&mut self, index: usize, len: usize) {
+        if self.slots.is_empty() {
+            return;
+        }
+        self.slot_of.resize(len, NO_ENTRY);
+        let slot = self.slot_of.swap_remove(index);
+        self.release(slot);
+        if index < self.slot_of.len() && self.slot_of[index] != NO_ENTRY {
+            self.slots[self.slot_of[index]].index = index;
+        }
+    }
+    /// Follow the release of the node at `index`, its slot is reused later
+    #[cfg(feature = "free-list")]
+    pub(super) fn forget(&mut self, index: usize) {
+        if let Some(slot) = self.slot_of.get_mut(index) {
+            let slot = core::mem::replace(slot, NO_ENTRY);
+            self.release(slot);
+        }
+    }
+    /// Make all the handles stale
+    pub(super) fn invalidate(&mut self) {
+        for slot in core::mem::take(&mut self.slot_of) {
+            self.release(slot);
+        }
+    }
+    fn release(&mut self, slot: usize) {
+        if slot != NO_ENTRY {
+            let slot_ref = &mut self.slots[slot];
+            slot_ref.index = NO_ENTRY;
+            slot_ref.generation = slot_ref.generation.wrapping_add(1);
+            self.free.push(slot);
+        }
+    }
+}
+
+impl<T: PartialOrd, A: Allocator, G: Augment<T>> RBBTree<T, A, G> {
+    /// Insert a new element into the Binary Tree, give back a handle to it
+    pub fn insert_with_handle(&mut self, content: T) -> Handle {
+        let index = self.insert_index(content);
+        self.handles.attach(index, self.data.len())
+    }
+    /// Get the element of a handle, `None` if it was removed
+    pub fn get_by_handle(&self, handle: Handle) -> Option<&T> {
+        let index = self.handles.resolve(handle)?;
+        Some(self.data[index].content())
+    }
+    /// Remove the element of a handle without comparing anything, `None` if it
+    /// was already removed
+    pub fn remove_by_handle(&mut self, handle: Handle) -> Option<T> {
+        let index = self.handles.resolve(handle)?;
+        Some(self.remove_index(index))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::RBBTree;
+    use rand::prelude::*;
+    #[test]
+    fn handles() {
+        let mut rng = rand::thread_rng();
+        let mut rnb = RBBTree::new();
+        let mut live: Vec<_> = (0..256)
+            .map(|_| {
+                let val: u32 = rng.gen_range(0..64);
+                (rnb.insert_with_handle(val), val)
+            })
+            .collect();
+        // Elements without handle never compare equal to the ones with a handle
+        let mut plain: Vec<u32> = (0..128).map(|_| rng.gen_range(64..128)).collect();
+        for val in plain.iter() {
+            rnb.insert(*val);
+        }
+        live.shuffle(&mut rng);
+        let mut stale = Vec::new();
+        for (handle, val) in live.drain(128..) {
+            assert_eq!(rnb.remove_by_handle(handle), Some(val));
+            stale.push(handle);
+            rnb.check_nodes();
+        }
+        // Removals by value also move the nodes around
+        plain.shuffle(&mut rng);
+        for val in plain.drain(64..) {
+            assert!(rnb.remove(&val));
+            rnb.check_nodes();
+        }
+        for (handle, val) in live.iter() {
+            assert_eq!(rnb.get_by_handle(*handle), Some(val));
+        }
+        for handle in stale {
+            assert_eq!(rnb.get_by_handle(handle), None);
+            assert_eq!(rnb.remove_by_handle(handle), None);
+        }
+        let handle = rnb.insert_with_handle(1000);
+        assert_eq!(rnb.get_by_handle(handle), Some(&1000));
+        let _ = rnb.split_off(&2000);
+        assert_eq!(rnb.get_by_handle(handle), None);
+    }
+}
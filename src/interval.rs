@@ -0,0 +1,265 @@
+//! Interval Tree based on the Red and Black Binary Tree
+use super::{after_end, Augment, Global, RBBTree, RBBTreeIterator, NO_ENTRY};
+use alloc::vec::Vec;
+use core::ops::{Bound, Range};
+
+/// Node content of the interval tree, ordered by start then by end
+struct IntervalEntry<K, V> {
+    interval: Range<K>,
+    value: V,
+}
+
+impl<K: PartialEq, V> PartialEq for IntervalEntry<K, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.interval == other.interval
+    }
+}
+impl<K: PartialOrd, V> PartialOrd for IntervalEntry<K, V> {
+    fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
+        cmp_interval(&self.interval, &other.interval)
+    }
+}
+
+fn cmp_interval<K: PartialOrd>(a: &Range<K>, b: &Range<K>) -> Option<core::cmp::Ordering> {
+    match a.start.partial_cmp(&b.start) {
+        Some(core::cmp::Ordering::Equal) => a.end.partial_cmp(&b.end),
+        ordering => ordering,
+    }
+}
+
+/// Check if `start < end` does not hold, an empty range contains no point
+fn is_empty_range<K: PartialOrd>(range: &Range<K>) -> bool {
+    range.start.partial_cmp(&range.end) != Some(core::cmp::Ordering::Less)
+}
+
+/// Augmentation keeping the greatest end of every subtree
+struct MaxEnd;
+
+impl<K: PartialOrd + Clone, V> Augment<IntervalEntry<K, V>> for MaxEnd {
+    type Value = Option<K>;
+    fn identity() -> Self::Value {
+        None
+    }
+    fn combine(
+        left: &Self::Value,
+        content: &IntervalEntry<K, V>,
+        right: &Self::Value,
+    ) -> Self::Value {
+        let mut max = &content.interval.end;
+        for end in [left, right].into_iter().flatten() {
+            if end > max {
+                max = end;
+            }
+        }
+        Some(max.clone())
+    }
+}
+
+/// Interval Tree Structure, intervals are half open `[start, end)`
+pub struct IntervalTree<K: PartialOrd + Clone, V> {
+    tree: RBBTree<IntervalEntry<K, V>, Global, MaxEnd>,
+}
+
+/// Iterator over the intervals of an Interval Tree, ordered by start
+pub struct IntervalTreeIterator<'a, K, V> {
+    inner: RBBTreeIterator<'a, IntervalEntry<K, V>, Global, Option<K>>,
+}
+
+/// Iterator over the intervals overlapping a query, ordered by start
+pub struct IntervalTreeOverlapping<'a, K: PartialOrd + Clone, V> {
+    tree: &'a RBBTree<IntervalEntry<K, V>, Global, MaxEnd>,
+    stack: Vec<usize>,
+    low: K,
+    high: Bound<K>,
+}
+
+impl<K: PartialOrd + Clone, V> IntervalTree<K, V> {
+    /// Create a new Interval Tree
+    pub fn new() -> Self {
+        Self {
+            tree: RBBTree::default(),
+        }
+    }
+    /// Number of intervals into the Interval Tree
+    pub fn len(&self) -> usize {
+        self.tree.n
+    }
+    /// Check if the Interval Tree contains no interval
+    pub fn is_empty(&self) -> bool {
+        self.tree.n == 0
+    }
+    /// Insert an interval associated to a value. The same interval may be inserted several times,
+    /// empty intervals are kept but never overlap anything
+    pub fn insert(&mut self, interval: Range<K>, value: V) {
+        self.tree.insert(IntervalEntry { interval, value });
+    }
+    /// Remove one occurence of the interval, give back its value
+    pub fn remove(&mut self, interval: &Range<K>) -> Option<V> {
+        let index = self
+            .tree
+            .find_by(|entry| cmp_interval(interval, &entry.interval));
+        (index != NO_ENTRY).then(|| self.tree.remove_index(index).value)
+    }
+    /// Create an iterator over all the intervals
+    pub fn iter(&self) -> IntervalTreeIterator<'_, K, V> {
+        IntervalTreeIterator {
+            inner: self.tree.iter(),
+        }
+    }
+    /// Create an iterator over the intervals overlapping `range`
+    pub fn overlapping(&self, range: Range<K>) -> IntervalTreeOverlapping<'_, K, V> {
+        // An empty or inverted range overlaps nothing
+        let root = if is_empty_range(&range) {
+            NO_ENTRY
+        } else {
+            self.tree.root.unwrap_or(NO_ENTRY)
+        };
+        self.query(root, range.start, Bound::Excluded(range.end))
+    }
+    /// Create an iterator over the intervals containing `point`
+    pub fn stabbing(&self, point: K) -> IntervalTreeOverlapping<'_, K, V> {
+        let root = self.tree.root.unwrap_or(NO_ENTRY);
+        self.query(root, point.clone(), Bound::Included(point))
+    }
+    /// Check if the Interval Tree is okay
+    #[cfg(any(debug_assertions, test))]
+    pub fn check_nodes(&self) {
+        self.tree.check_nodes();
+    }
+    fn query(&self, root: usize, low: K, high: Bound<K>) -> IntervalTreeOverlapping<'_, K, V> {
+        let mut overlapping = IntervalTreeOverlapping {
+            tree: &self.tree,
+            stack: Vec::new(),
+            low,
+            high,
+        };
+        overlapping.push_left(root);
+        overlapping
+    }
+}
+impl<K: PartialOrd + Clone, V> Default for IntervalTree<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a, K: PartialOrd + Clone, V> IntervalTreeOverlapping<'a, K, V> {
+    /// Stack the left spine, skipping the subtrees ending before the query
+    fn push_left(&mut self, mut x: usize) {
+        let data = &self.tree.data;
+        while x != NO_ENTRY && matches!(&data[x].summary, Some(end) if *end > self.low) {
+            self.stack.push(x);
+            x = data[x].left();
+        }
+    }
+}
+
+impl<'a, K, V> Iterator for IntervalTreeIterator<'a, K, V> {
+    type Item = (&'a Range<K>, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner
+            .next()
+            .map(|entry| (&entry.interval, &entry.value))
+    }
+}
+
+impl<'a, K: PartialOrd + Clone, V> Iterator for IntervalTreeOverlapping<'a, K, V> {
+    type Item = (&'a Range<K>, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(x) = self.stack.pop() {
+            let entry = self.tree.data[x].content();
+            if after_end(self.high.as_ref(), &entry.interval.start) {
+                // All the following intervals start after the query
+                self.stack.clear();
+                return None;
+            }
+            self.push_left(self.tree.data[x].right());
+            if entry.interval.end > self.low && !is_empty_range(&entry.interval) {
+                return Some((&entry.interval, &entry.value));
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::IntervalTree;
+    use core::ops::Range;
+    use rand::prelude::*;
+    #[test]
+    fn overlapping_and_stabbing() {
+        let mut rng = rand::thread_rng();
+        let mut tree = IntervalTree::new();
+        let mut v = Vec::new();
+        for i in 0..256 {
+            let start = rng.gen_range(0..1000);
+            let interval = start..start + rng.gen_range(1..64);
+            tree.insert(interval.clone(), i);
+            v.push((interval, i));
+            tree.check_nodes();
+        }
+        v.shuffle(&mut rng);
+        let removed: Vec<_> = v[128..]
+            .iter()
+            .map(|(interval, _)| interval.clone())
+            .collect();
+        for interval in removed {
+            // Equal intervals may be removed in any order, drop the pair actually removed
+            let value = tree.remove(&interval).unwrap();
+            let pos = v
+                .iter()
+                .position(|(i, val)| *i == interval && *val == value)
+                .unwrap();
+            v.swap_remove(pos);
+            assert_eq!(tree.remove(&(interval.start..interval.start)), None);
+            tree.check_nodes();
+        }
+        assert_eq!(tree.len(), v.len());
+        let sorted = |mut r: Vec<(std::ops::Range<u32>, i32)>| {
+            r.sort_by_key(|(interval, i)| (interval.start, interval.end, *i));
+            r
+        };
+        for _i in 0..128 {
+            let a = rng.gen_range(0..1100);
+            let query = a..a + rng.gen_range(1..32);
+            let found = tree
+                .overlapping(query.clone())
+                .map(|(interval, i)| (interval.clone(), *i))
+                .collect();
+            let expected = v
+                .iter()
+                .filter(|(interval, _)| interval.start < query.end && query.start < interval.end)
+                .cloned()
+                .collect();
+            assert_eq!(sorted(found), sorted(expected));
+            let found = tree
+                .stabbing(a)
+                .map(|(interval, i)| (interval.clone(), *i))
+                .collect();
+            let expected = v
+                .iter()
+                .filter(|(interval, _)| interval.contains(&a))
+                .cloned()
+                .collect();
+            assert_eq!(sorted(found), sorted(expected));
+        }
+    }
+    #[test]
+    fn empty_intervals() {
+        let mut tree = IntervalTree::new();
+        tree.insert(0..10, "wide");
+        tree.insert(5..5, "empty");
+        tree.insert(Range { start: 7, end: 3 }, "inverted");
+        tree.check_nodes();
+        assert_eq!(tree.len(), 3);
+        assert_eq!(tree.overlapping(5..5).count(), 0);
+        let inverted = Range { start: 10, end: 5 };
+        assert_eq!(tree.overlapping(inverted).count(), 0);
+        assert!(tree.overlapping(0..20).map(|(_, v)| *v).eq(["wide"]));
+        assert!(tree.stabbing(5).map(|(_, v)| *v).eq(["wide"]));
+        assert_eq!(tree.remove(&(5..5)), Some("empty"));
+    }
+}
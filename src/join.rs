@@ -0,0 +1,369 @@
+//! Split and join of Red and Black Binary Trees
+//!
+//! Subtrees are joined by black height, so the rebalancing costs O(log n). The
+//! nodes of both trees must live into the same arena, so the smallest arena is
+//! always moved into the biggest one.
+use super::{Allocator, Augment, Color, RBBTree, NO_ENTRY};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+
+impl<T: PartialOrd, A: Allocator, G: Augment<T>> RBBTree<T, A, G> {
+    /// Join two trees around a pivot element. All the elements of `left` must be
+    /// lower or equal to `pivot`, and all the elements of `right` greater or equal
+    ///
+    /// Panics if the order is not respected
+    pub fn join(mut left: Self, pivot: T, mut right: Self) -> Self {
+        assert!(
+            left.last_index() == NO_ENTRY || *left.data[left.last_index()].content() <= pivot,
+            "join: left tree goes beyond the pivot"
+        );
+        assert!(
+            right.first_index() == NO_ENTRY || pivot <= *right.data[right.first_index()].content(),
+            "join: right tree goes before the pivot"
+        );
+        let (left_root, right_root);
+        if left.data.len() >= right.data.len() {
+            left_root = left.root.unwrap_or(NO_ENTRY);
+            right_root = left.absorb(&mut right);
+        } else {
+            core::mem::swap(&mut left, &mut right);
+            right_root = left.root.unwrap_or(NO_ENTRY);
+            left_root = left.absorb(&mut right);
+        }
+        left.n += 1;
+        let pivot = left.alloc_node(pivot);
+        let root = left.join_nodes(left_root, pivot, right_root);
+        left.root = Some(root);
+        left.handles.invalidate();
+        left
+    }
+    /// Split the tree in two, the elements greater or equal to `value` are moved
+    /// into the returned tree
+    pub fn split_off<Q>(&mut self, value: &Q) -> Self
+    where
+        T: Borrow<Q>,
+        Q: PartialOrd + ?Sized,
+        A: Clone,
+    {
+        self.handles.invalidate();
+        let mut other = self.new_sibling();
+        let Some(root) = self.root else {
+            return other;
+        };
+        let (left, right) =
+            self.split_nodes(root, &|tree, x| tree.data[x].content().borrow() < value);
+        // Move the smallest part out of the arena
+        let (keep, extract) = if self.is_smaller(left, right) {
+            (right, left)
+        } else {
+            (left, right)
+        };
+        self.root = (keep != NO_ENTRY).then_some(keep);
+        self.extract(extract, &mut other);
+        if keep == right {
+            core::mem::swap(self, &mut other);
+        }
+        other
+    }
+    /// Move all the elements of `other` into the tree, leaving `other` empty.
+    /// Equal elements of `other` go after the ones of the tree
+    pub fn append(&mut self, other: &mut Self) {
+        if other.n == 0 {
+            return;
+        }
+        self.handles.invalidate();
+        other.handles.invalidate();
+        let swapped = self.data.len() < other.data.len();
+        if swapped {
+            core::mem::swap(self, other);
+        }
+        let own_root = self.root.unwrap_or(NO_ENTRY);
+        let other_root = self.absorb(other);
+        let root = if swapped {
+            self.union_nodes(other_root, own_root)
+        } else {
+            self.union_nodes(own_root, other_root)
+        };
+        self.root = (root != NO_ENTRY).then_some(root);
+    }
+    /// Move the nodes of `other` at the end of the arena, give back the index
+    /// of its root. `other` is emptied
+    fn absorb(&mut self, other: &mut Self) -> usize {
+        #[cfg(feature = "free-list")]
+        other.compact();
+        let offset = self.data.len();
+        let shift = |x: usize| if x == NO_ENTRY { NO_ENTRY } else { x + offset };
+        let other_root = other.root.map_or(NO_ENTRY, shift);
+        self.n += other.n;
+        other.root = None;
+        other.n = 0;
+        for mut node in other.data.drain(..) {
+            node.set_parent(shift(node.parent()));
+            node.set_left(shift(node.left()));
+            node.set_right(shift(node.right()));
+            self.data.push(node);
+        }
+        other_root
+    }
+    /// Move the subtree `x` into the empty tree `other`
+    fn extract(&mut self, x: usize, other: &mut Self) {
+        let mut indexes = Vec::new();
+        let mut stack = vec![x];
+        while let Some(x) = stack.pop() {
+            if x != NO_ENTRY {
+                indexes.push(x);
+                stack.push(self.data[x].left());
+                stack.push(self.data[x].right());
+            }
+        }
+        indexes.sort_unstable();
+        // Removing in decreasing order, swap_remove only moves the nodes we keep
+        let mut nodes: Vec<_> = indexes.iter().rev().map(|x| self.take_node(*x)).collect();
+        self.n -= indexes.len();
+        let relocate = |x: usize| match x {
+            NO_ENTRY => NO_ENTRY,
+            x => indexes.binary_search(&x).unwrap(),
+        };
+        other.data.reserve(nodes.len());
+        while let Some(mut node) = nodes.pop() {
+            node.set_parent(relocate(node.parent()));
+            node.set_left(relocate(node.left()));
+            node.set_right(relocate(node.right()));
+            other.data.push(node);
+        }
+        other.n = indexes.len();
+        other.root = (x != NO_ENTRY).then(|| relocate(x));
+    }
+    /// Split the subtree `x` between the nodes going to the left and the other
+    /// ones, give back the two new roots
+    fn split_nodes(
+        &mut self,
+        x: usize,
+        goes_left: &dyn Fn(&Self, usize) -> bool,
+    ) -> (usize, usize) {
+        if x == NO_ENTRY {
+            return (NO_ENTRY, NO_ENTRY);
+        }
+        let (l, r) = self.expose(x);
+        if goes_left(self, x) {
+            let (rl, rr) = self.split_nodes(r, goes_left);
+            (self.join_nodes(l, x, rl), rr)
+        } else {
+            let (ll, lr) = self.split_nodes(l, goes_left);
+            (ll, self.join_nodes(lr, x, r))
+        }
+    }
+    /// Merge two subtrees of the arena, give back the new root. Elements of `a`
+    /// go before the equal elements of `b`
+    pub(super) fn union_nodes(&mut self, a: usize, b: usize) -> usize {
+        if a == NO_ENTRY {
+            return b;
+        } else if b == NO_ENTRY {
+            return a;
+        }
+        let (bl, br) = self.expose(b);
+        let (al, ar) = self.split_nodes(a, &|tree, x| {
+            *tree.data[x].content() <= *tree.data[b].content()
+        });
+        let l = self.union_nodes(al, bl);
+        let r = self.union_nodes(ar, br);
+        self.join_nodes(l, b, r)
+    }
+    /// Detach the children of a node, they become black roots
+    fn expose(&mut self, x: usize) -> (usize, usize) {
+        let (l, r) = (self.data[x].left(), self.data[x].right());
+        for child in [l, r] {
+            if child != NO_ENTRY {
+                self.data[child].set_parent(NO_ENTRY);
+                set_black!(self.data[child]);
+            }
+        }
+        let node = &mut self.data[x];
+        node.set_left(NO_ENTRY);
+        node.set_right(NO_ENTRY);
+        node.set_parent(NO_ENTRY);
+        (l, r)
+    }
+    /// Join two subtrees rooted on black nodes with the lonely node `pivot`,
+    /// give back the new root
+    fn join_nodes(&mut self, left: usize, pivot: usize, right: usize) -> usize {
+        let (bh_left, bh_right) = (self.black_height(left), self.black_height(right));
+        set_red!(self.data[pivot]);
+        if bh_left == bh_right {
+            self.data[pivot].set_left(left);
+            self.data[pivot].set_right(right);
+            for child in [left, right] {
+                if child != NO_ENTRY {
+                    self.data[child].set_parent(pivot);
+                }
+            }
+            self.set_as_root(pivot);
+            self.update_node(pivot);
+            return pivot;
+        }
+        let high = if bh_left > bh_right { left } else { right };
+        // Find the black node of the high tree spine with the same black height
+        // than the low tree. Its old place is taken by the pivot
+        let (mut c, mut h, mut parent) = (high, bh_left.max(bh_right), NO_ENTRY);
+        let low = bh_left.min(bh_right);
+        while c != NO_ENTRY && !(is_black!(self.data[c]) && h == low) {
+            if is_black!(self.data[c]) {
+                h -= 1;
+            }
+            parent = c;
+            c = if high == left {
+                self.data[c].right()
+            } else {
+                self.data[c].left()
+            };
+        }
+        self.data[pivot].set_parent(parent);
+        if high == left {
+            self.data[parent].set_right(pivot);
+            self.data[pivot].set_left(c);
+            self.data[pivot].set_right(right);
+        } else {
+            self.data[parent].set_left(pivot);
+            self.data[pivot].set_left(left);
+            self.data[pivot].set_right(c);
+        }
+        for child in [self.data[pivot].left(), self.data[pivot].right()] {
+            if child != NO_ENTRY {
+                self.data[child].set_parent(pivot);
+            }
+        }
+        self.root = Some(high);
+        self.update_path(pivot);
+        self.insert_recurse(pivot);
+        self.root.unwrap()
+    }
+    /// Number of black nodes from `x` to any leaf
+    fn black_height(&self, mut x: usize) -> usize {
+        let mut h = 0;
+        while x != NO_ENTRY {
+            if is_black!(self.data[x]) {
+                h += 1;
+            }
+            x = self.data[x].left();
+        }
+        h
+    }
+    /// Check if the subtree `a` has fewer nodes than the subtree `b`. Without
+    /// subtree sizes both are walked together, so only the smallest one is
+    /// walked completely
+    fn is_smaller(&self, a: usize, b: usize) -> bool {
+        #[cfg(feature = "order-statistics")]
+        {
+            self.size(a) < self.size(b)
+        }
+        #[cfg(not(feature = "order-statistics"))]
+        {
+            // Visit one more node of the subtree, false once it is exhausted
+            let visit = |stack: &mut Vec<usize>| {
+                while let Some(x) = stack.pop() {
+                    if x != NO_ENTRY {
+                        stack.push(self.data[x].left());
+                        stack.push(self.data[x].right());
+                        return true;
+                    }
+                }
+                false
+            };
+            let (mut a, mut b) = (vec![a], vec![b]);
+            loop {
+                match (visit(&mut a), visit(&mut b)) {
+                    (true, true) => continue,
+                    (more_a, more_b) => return !more_a && more_b,
+                }
+            }
+        }
+    }
+    /// Create an empty tree sharing the same allocator
+    fn new_sibling(&self) -> Self
+    where
+        A: Clone,
+    {
+        Self {
+            #[cfg(nightly)]
+            data: Vec::new_in(self.data.allocator().clone()),
+            #[cfg(not(nightly))]
+            data: Vec::new(),
+            root: None,
+            n: 0,
+            handles: Default::default(),
+            #[cfg(feature = "free-list")]
+            free: NO_ENTRY,
+            #[cfg(not(nightly))]
+            phantom: core::marker::PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::RBBTree;
+    use rand::prelude::*;
+    #[test]
+    fn split_and_join() {
+        let mut rng = rand::thread_rng();
+        for _i in 0..64 {
+            let mut v: Vec<u32> = (0..rng.gen_range(0..256))
+                .map(|_| rng.gen_range(0..512))
+                .collect();
+            let mut rnb = RBBTree::new();
+            for val in v.iter() {
+                rnb.insert(*val);
+            }
+            v.sort();
+            let key = rng.gen_range(0..520);
+            let capacity = rnb.capacity();
+            let right = rnb.split_off(&key);
+            rnb.check_nodes();
+            right.check_nodes();
+            // The biggest part stays into the original arena
+            let kept = if rnb.n >= right.n { &rnb } else { &right };
+            assert_eq!(kept.capacity(), capacity);
+            let pos = v.partition_point(|x| *x < key);
+            assert!(rnb.iter().eq(v[..pos].iter()));
+            assert!(right.iter().eq(v[pos..].iter()));
+            let joined = RBBTree::join(rnb, key, right);
+            joined.check_nodes();
+            v.insert(pos, key);
+            assert!(joined.iter().eq(v.iter()));
+        }
+    }
+    #[test]
+    fn append() {
+        let mut rng = rand::thread_rng();
+        for _i in 0..64 {
+            let mut a = RBBTree::new();
+            let mut b = RBBTree::new();
+            let mut v = Vec::new();
+            for _j in 0..rng.gen_range(0..128) {
+                let val: u32 = rng.gen_range(0..256);
+                a.insert(val);
+                v.push(val);
+            }
+            let shift = rng.gen_range(0..256);
+            for _j in 0..rng.gen_range(0..128) {
+                let val: u32 = rng.gen_range(shift..shift + 64);
+                b.insert(val);
+                v.push(val);
+            }
+            a.append(&mut b);
+            a.check_nodes();
+            b.check_nodes();
+            v.sort();
+            assert!(a.iter().eq(v.iter()));
+            assert_eq!(b.iter().next(), None);
+        }
+    }
+    #[test]
+    #[should_panic]
+    fn unordered_join() {
+        let mut left = RBBTree::new();
+        left.insert(3);
+        RBBTree::join(left, 2, RBBTree::new());
+    }
+}
@@ -2,87 +2,337 @@
 #![deny(missing_docs)]
 #![cfg_attr(nightly, feature(allocator_api))]
 #![cfg_attr(nightly, feature(nonnull_slice_from_raw_parts))]
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
 
 #[cfg(nightly)]
-use std::alloc::{Allocator, Global};
+use alloc::alloc::{Allocator, Global};
+use alloc::collections::TryReserveError;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use core::ops::{Bound, RangeBounds};
+
+macro_rules! set_black {
+    ($item:expr) => {
+        $item.set_color(Color::Black);
+    };
+}
+macro_rules! set_red {
+    ($item:expr) => {
+        $item.set_color(Color::Red);
+    };
+}
+macro_rules! is_black {
+    ($item:expr) => {
+        $item.color() as u64 == Color::Black as u64
+    };
+}
+macro_rules! is_red {
+    ($item:expr) => {
+        $item.color() as u64 == Color::Red as u64
+    };
+}
 
-/// Main Structure
-pub struct RBBTree<T: std::cmp::PartialOrd, A: Allocator = Global> {
+mod augment;
+mod cursor;
+mod handle;
+mod interval;
+mod join;
+mod map;
+mod persistent;
+#[cfg(feature = "serde")]
+mod serialize;
+mod set;
+mod snapshot;
+mod unique;
+pub use augment::Augment;
+pub use cursor::{Cursor, CursorMut};
+pub use handle::Handle;
+pub use interval::{IntervalTree, IntervalTreeIterator, IntervalTreeOverlapping};
+pub use map::{RBBMap, RBBMapIterator, RBBMapKeys, RBBMapValues};
+pub use persistent::{PersistentRBBTree, PersistentRBBTreeIterator};
+pub use set::{RBBTreeDifference, RBBTreeIntersection, RBBTreeSymmetricDifference, RBBTreeUnion};
+pub use snapshot::{Encode, SnapshotError};
+pub use unique::RBBSet;
+
+/// Main Structure, a multiset: equal elements are all kept and iterate in
+/// insertion order. `RBBSet` keeps unique elements
+pub struct RBBTree<T: core::cmp::PartialOrd, A: Allocator = Global, G: Augment<T> = ()> {
     #[cfg(nightly)]
-    data: Vec<Node<T>, A>,
+    data: Vec<Node<T, G::Value>, A>,
     #[cfg(not(nightly))]
-    data: Vec<Node<T>>,
+    data: Vec<Node<T, G::Value>>,
     root: Option<usize>,
     n: usize,
+    handles: handle::HandleTable,
+    /// Head of the free slots, chained by their right link
+    #[cfg(feature = "free-list")]
+    free: usize,
     #[cfg(not(nightly))]
-    phantom: std::marker::PhantomData<A>,
+    phantom: core::marker::PhantomData<A>,
 }
 
-#[repr(u64)]
 #[derive(Debug, Copy, Clone)]
 enum Color {
     Red,
     Black,
 }
 
-#[repr(C, align(64))]
+/// Integer type of the links stored into the nodes
+#[cfg(not(feature = "compact"))]
+type Index = usize;
+/// Integer type of the links stored into the nodes, the highest bit of the
+/// parent link holds the color
+#[cfg(feature = "compact")]
+type Index = u32;
+
+#[cfg_attr(feature = "cache-aligned", repr(C, align(64)))]
 #[derive(Debug)]
-struct Node<T> {
+struct Node<T, S = ()> {
+    #[cfg(not(feature = "free-list"))]
     content: T,
+    /// `None` while the slot waits into the free list
+    #[cfg(feature = "free-list")]
+    content: Option<T>,
+    summary: S,
+    #[cfg(not(feature = "compact"))]
     color: Color,
-    parent: usize,
-    left: usize,
-    right: usize,
+    parent: Index,
+    left: Index,
+    right: Index,
+    #[cfg(feature = "order-statistics")]
+    size: Index,
 }
 
 const NO_ENTRY: usize = usize::MAX;
+/// `NO_ENTRY` once stored into a compact link
+#[cfg(feature = "compact")]
+const NIL: Index = Index::MAX >> 1;
+#[cfg(feature = "compact")]
+const COLOR_BIT: Index = !NIL;
+
+#[cfg(not(feature = "compact"))]
+#[inline(always)]
+fn to_index(x: usize) -> Index {
+    x
+}
+#[cfg(not(feature = "compact"))]
+#[inline(always)]
+fn from_index(x: Index) -> usize {
+    x
+}
+#[cfg(feature = "compact")]
+#[inline(always)]
+fn to_index(x: usize) -> Index {
+    if x == NO_ENTRY {
+        NIL
+    } else {
+        assert!(x < NIL as usize, "too many nodes for compact links");
+        x as Index
+    }
+}
+#[cfg(feature = "compact")]
+#[inline(always)]
+fn from_index(x: Index) -> usize {
+    if x == NIL {
+        NO_ENTRY
+    } else {
+        x as usize
+    }
+}
 
-impl<T: std::cmp::PartialOrd> Node<T> {
-    fn new(content: T) -> Self {
+impl<T: core::cmp::PartialOrd, S> Node<T, S> {
+    fn new(content: T, summary: S) -> Self {
         Self {
+            #[cfg(not(feature = "free-list"))]
             content,
+            #[cfg(feature = "free-list")]
+            content: Some(content),
+            summary,
+            #[cfg(not(feature = "compact"))]
             color: Color::Red,
-            parent: NO_ENTRY,
-            left: NO_ENTRY,
-            right: NO_ENTRY,
+            parent: to_index(NO_ENTRY),
+            left: to_index(NO_ENTRY),
+            right: to_index(NO_ENTRY),
+            #[cfg(feature = "order-statistics")]
+            size: 1,
         }
     }
 }
-impl<T> Drop for Node<T> {
-    fn drop(&mut self) {}
+
+impl<T, S> Node<T, S> {
+    #[inline(always)]
+    fn left(&self) -> usize {
+        from_index(self.left)
+    }
+    #[inline(always)]
+    fn set_left(&mut self, x: usize) {
+        self.left = to_index(x);
+    }
+    #[inline(always)]
+    fn right(&self) -> usize {
+        from_index(self.right)
+    }
+    #[inline(always)]
+    fn set_right(&mut self, x: usize) {
+        self.right = to_index(x);
+    }
+}
+
+#[cfg(not(feature = "free-list"))]
+impl<T, S> Node<T, S> {
+    #[inline(always)]
+    fn content(&self) -> &T {
+        &self.content
+    }
+    #[inline(always)]
+    fn content_mut(&mut self) -> &mut T {
+        &mut self.content
+    }
+    #[inline(always)]
+    fn into_content(self) -> T {
+        self.content
+    }
+}
+
+#[cfg(feature = "free-list")]
+impl<T, S> Node<T, S> {
+    #[inline(always)]
+    fn content(&self) -> &T {
+        self.content.as_ref().unwrap()
+    }
+    #[inline(always)]
+    fn content_mut(&mut self) -> &mut T {
+        self.content.as_mut().unwrap()
+    }
+    #[inline(always)]
+    fn into_content(self) -> T {
+        self.content.unwrap()
+    }
+    #[inline(always)]
+    fn is_free(&self) -> bool {
+        self.content.is_none()
+    }
+    /// Empty slot of the free list, followed by the slot `next`
+    fn vacant(next: usize, summary: S) -> Self {
+        Self {
+            content: None,
+            summary,
+            #[cfg(not(feature = "compact"))]
+            color: Color::Black,
+            parent: to_index(NO_ENTRY),
+            left: to_index(NO_ENTRY),
+            right: to_index(next),
+            #[cfg(feature = "order-statistics")]
+            size: 0,
+        }
+    }
+}
+
+#[cfg(not(feature = "compact"))]
+impl<T, S> Node<T, S> {
+    #[inline(always)]
+    fn parent(&self) -> usize {
+        self.parent
+    }
+    #[inline(always)]
+    fn set_parent(&mut self, x: usize) {
+        self.parent = x;
+    }
+    #[inline(always)]
+    fn color(&self) -> Color {
+        self.color
+    }
+    #[inline(always)]
+    fn set_color(&mut self, color: Color) {
+        self.color = color;
+    }
+    #[cfg(feature = "order-statistics")]
+    #[inline(always)]
+    fn size(&self) -> usize {
+        self.size
+    }
+    #[cfg(feature = "order-statistics")]
+    #[inline(always)]
+    fn set_size(&mut self, size: usize) {
+        self.size = size;
+    }
+}
+
+#[cfg(feature = "compact")]
+impl<T, S> Node<T, S> {
+    #[inline(always)]
+    fn parent(&self) -> usize {
+        from_index(self.parent & NIL)
+    }
+    #[inline(always)]
+    fn set_parent(&mut self, x: usize) {
+        self.parent = (self.parent & COLOR_BIT) | to_index(x);
+    }
+    #[inline(always)]
+    fn color(&self) -> Color {
+        if self.parent & COLOR_BIT == 0 {
+            Color::Red
+        } else {
+            Color::Black
+        }
+    }
+    #[inline(always)]
+    fn set_color(&mut self, color: Color) {
+        match color {
+            Color::Red => self.parent &= NIL,
+            Color::Black => self.parent |= COLOR_BIT,
+        }
+    }
+    #[cfg(feature = "order-statistics")]
+    #[inline(always)]
+    fn size(&self) -> usize {
+        self.size as usize
+    }
+    #[cfg(feature = "order-statistics")]
+    #[inline(always)]
+    fn set_size(&mut self, size: usize) {
+        self.size = size as Index;
+    }
 }
 
 /// Iterator over Red and Black Binary Tree
-pub struct RBBTreeIterator<'a, T, A: Allocator = Global> {
+pub struct RBBTreeIterator<'a, T, A: Allocator = Global, S = ()> {
     #[cfg(not(nightly))]
-    data: &'a Vec<Node<T>>,
+    data: &'a Vec<Node<T, S>>,
     #[cfg(nightly)]
-    data: &'a Vec<Node<T>, A>,
-    x: usize,
-    stack: Vec<usize>,
+    data: &'a Vec<Node<T, S>, A>,
+    front: usize,
+    back: usize,
     #[cfg(not(nightly))]
-    phantom: std::marker::PhantomData<A>,
+    phantom: core::marker::PhantomData<A>,
 }
 
-macro_rules! set_black {
-    ($item:expr) => {
-        $item.color = Color::Black;
-    };
+/// Iterator over a range of the Red and Black Binary Tree
+pub struct RBBTreeRange<'a, T, A: Allocator = Global, S = ()> {
+    inner: RBBTreeIterator<'a, T, A, S>,
 }
-macro_rules! set_red {
-    ($item:expr) => {
-        $item.color = Color::Red;
-    };
-}
-macro_rules! is_black {
-    ($item:expr) => {
-        $item.color as u64 == Color::Black as u64
-    };
+
+/// Owning iterator over Red and Black Binary Tree
+pub struct RBBTreeIntoIter<T, A: Allocator = Global, S = ()> {
+    #[cfg(not(nightly))]
+    inner: alloc::vec::IntoIter<Node<T, S>>,
+    #[cfg(nightly)]
+    inner: alloc::vec::IntoIter<Node<T, S>, A>,
+    #[cfg(not(nightly))]
+    phantom: core::marker::PhantomData<A>,
 }
-macro_rules! is_red {
-    ($item:expr) => {
-        $item.color as u64 == Color::Red as u64
-    };
+
+/// Draining iterator over Red and Black Binary Tree
+pub struct RBBTreeDrain<'a, T, A: Allocator = Global, S = ()> {
+    #[cfg(not(nightly))]
+    inner: alloc::vec::Drain<'a, Node<T, S>>,
+    #[cfg(nightly)]
+    inner: alloc::vec::Drain<'a, Node<T, S>, A>,
+    #[cfg(not(nightly))]
+    phantom: core::marker::PhantomData<A>,
 }
 
 #[cfg(not(nightly))]
@@ -90,257 +340,828 @@ macro_rules! is_red {
 pub trait Allocator {}
 #[cfg(not(nightly))]
 /// Dummy Globale struct for Stable rust
+#[derive(Clone, Copy)]
 pub struct Global {}
 #[cfg(not(nightly))]
 impl Allocator for Global {}
 
-impl<T: std::cmp::PartialOrd> RBBTree<T> {
+impl<T: core::cmp::PartialOrd> RBBTree<T> {
     /// Create a new Binary Tree
     pub fn new() -> Self {
+        Self::default()
+    }
+    /// Create a new Binary Tree with room for `capacity` elements
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            data: Vec::with_capacity(capacity),
+            root: None,
+            n: 0,
+            handles: Default::default(),
+            #[cfg(feature = "free-list")]
+            free: NO_ENTRY,
+            #[cfg(not(nightly))]
+            phantom: core::marker::PhantomData,
+        }
+    }
+    /// Create a Binary Tree from sorted elements in O(n). If the elements turn
+    /// out not to be sorted, every sorted run is built then merged
+    pub fn from_sorted_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut tree = Self::new();
+        tree.extend_sorted(iter);
+        tree
+    }
+}
+impl<T: core::cmp::PartialOrd, G: Augment<T>> Default for RBBTree<T, Global, G> {
+    /// Create a new Binary Tree, this is also the constructor of augmented trees
+    fn default() -> Self {
         Self {
             data: Vec::new(),
             root: None,
             n: 0,
+            handles: Default::default(),
+            #[cfg(feature = "free-list")]
+            free: NO_ENTRY,
             #[cfg(not(nightly))]
-            phantom: std::marker::PhantomData,
+            phantom: core::marker::PhantomData,
         }
     }
 }
-impl<T: std::cmp::PartialOrd, A: Allocator> RBBTree<T, A> {
-    #[cfg(nightly)]
+#[cfg(nightly)]
+impl<T: core::cmp::PartialOrd, A: Allocator> RBBTree<T, A> {
     /// Create a new Binary Tree with Custom Allocator
     pub fn new_in(alloc: A) -> Self {
         Self {
             data: Vec::new_in(alloc),
             root: None,
             n: 0,
+            handles: Default::default(),
+            #[cfg(feature = "free-list")]
+            free: NO_ENTRY,
         }
     }
+    /// Create a new Binary Tree with Custom Allocator and room for `capacity` elements
+    pub fn with_capacity_in(capacity: usize, alloc: A) -> Self {
+        Self {
+            data: Vec::with_capacity_in(capacity, alloc),
+            root: None,
+            n: 0,
+            handles: Default::default(),
+            #[cfg(feature = "free-list")]
+            free: NO_ENTRY,
+        }
+    }
+}
+impl<T: core::cmp::PartialOrd, A: Allocator, G: Augment<T>> RBBTree<T, A, G> {
+    /// A zero sized summary carries no information, so it is never computed
+    const AUGMENTED: bool = core::mem::size_of::<G::Value>() != 0;
     /// Create an iterator over the Binary Tree
-    pub fn iter<'a>(&'a self) -> RBBTreeIterator<'a, T, A> {
+    pub fn iter<'a>(&'a self) -> RBBTreeIterator<'a, T, A, G::Value> {
+        self.iter_between(self.first_index(), self.last_index())
+    }
+    /// Create an iterator over the elements contained into the range
+    pub fn range<'a, Q, R>(&'a self, range: R) -> RBBTreeRange<'a, T, A, G::Value>
+    where
+        T: Borrow<Q>,
+        Q: core::cmp::PartialOrd + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        let mut front = self.lower_bound_index(range.start_bound());
+        let back = self.upper_bound_index(range.end_bound());
+        if front != NO_ENTRY && after_end(range.end_bound(), self.data[front].content().borrow()) {
+            // Empty range, the start is already beyond the end
+            front = NO_ENTRY;
+        }
+        RBBTreeRange {
+            inner: self.iter_between(front, back),
+        }
+    }
+    /// Index of the first element not located before the start bound
+    fn lower_bound_index<Q>(&self, start: Bound<&Q>) -> usize
+    where
+        T: Borrow<Q>,
+        Q: core::cmp::PartialOrd + ?Sized,
+    {
+        let mut found = NO_ENTRY;
+        let mut index = self.root.unwrap_or(NO_ENTRY);
+        while index != NO_ENTRY {
+            if before_start(start, self.data[index].content().borrow()) {
+                index = self.data[index].right();
+            } else {
+                found = index;
+                index = self.data[index].left();
+            }
+        }
+        found
+    }
+    /// Index of the last element not located after the end bound
+    fn upper_bound_index<Q>(&self, end: Bound<&Q>) -> usize
+    where
+        T: Borrow<Q>,
+        Q: core::cmp::PartialOrd + ?Sized,
+    {
+        let mut found = NO_ENTRY;
+        let mut index = self.root.unwrap_or(NO_ENTRY);
+        while index != NO_ENTRY {
+            if after_end(end, self.data[index].content().borrow()) {
+                index = self.data[index].left();
+            } else {
+                found = index;
+                index = self.data[index].right();
+            }
+        }
+        found
+    }
+    fn iter_between<'a>(
+        &'a self,
+        front: usize,
+        back: usize,
+    ) -> RBBTreeIterator<'a, T, A, G::Value> {
+        let empty = front == NO_ENTRY || back == NO_ENTRY;
         RBBTreeIterator {
             data: &self.data,
-            x: self.root.unwrap_or(NO_ENTRY),
-            stack: Vec::new(),
+            front: if empty { NO_ENTRY } else { front },
+            back: if empty { NO_ENTRY } else { back },
             #[cfg(not(nightly))]
-            phantom: std::marker::PhantomData,
+            phantom: core::marker::PhantomData,
+        }
+    }
+    /// Remove all the elements of the Binary Tree in order, keeping its allocated memory
+    pub fn drain(&mut self) -> RBBTreeDrain<'_, T, A, G::Value> {
+        self.sort_data();
+        RBBTreeDrain {
+            inner: self.data.drain(..),
+            #[cfg(not(nightly))]
+            phantom: core::marker::PhantomData,
         }
     }
 
-    /// Insert a single element into the Binary Tree
+    /// Insert a single element into the Binary Tree, after the elements equal to it
     pub fn insert(&mut self, content: T) {
-        self.n += 1;
-        match self.root {
-            Some(mut index) => {
-                index = loop {
-                    if content < self.data[index].content {
-                        if self.data[index].left != NO_ENTRY {
-                            index = self.data[index].left;
-                        } else {
-                            self.data.push(Node::new(content));
-                            let new_index = self.data.len() - 1;
-                            self.data[index].left = new_index;
-                            self.data[new_index].parent = index;
-                            break new_index;
-                        }
-                    } else {
-                        if self.data[index].right != NO_ENTRY {
-                            index = self.data[index].right;
-                        } else {
-                            self.data.push(Node::new(content));
-                            let new_index = self.data.len() - 1;
-                            self.data[index].right = new_index;
-                            self.data[new_index].parent = index;
-                            break new_index;
-                        }
-                    }
-                };
-                self.insert_recurse(index);
+        self.insert_index(content);
+    }
+    /// Insert a new element, give back its index into the arena
+    fn insert_index(&mut self, content: T) -> usize {
+        let (mut parent, mut left) = (NO_ENTRY, false);
+        let mut index = self.root.unwrap_or(NO_ENTRY);
+        while index != NO_ENTRY {
+            parent = index;
+            left = content < *self.data[index].content();
+            index = if left {
+                self.data[index].left()
+            } else {
+                self.data[index].right()
+            };
+        }
+        self.insert_at(parent, left, content)
+    }
+    /// Insert a new element into the Binary Tree, the element is given back if
+    /// the arena can not grow
+    pub fn try_insert(&mut self, content: T) -> Result<(), (T, TryReserveError)> {
+        // A free slot is reused without allocating
+        #[cfg(feature = "free-list")]
+        let full = self.free == NO_ENTRY;
+        #[cfg(not(feature = "free-list"))]
+        let full = true;
+        if full {
+            if let Err(e) = self.data.try_reserve(1) {
+                return Err((content, e));
             }
-            None => {
-                self.data.push(Node::new(content));
-                self.data[0].color = Color::Black;
-                self.root = Some(0);
+        }
+        self.insert(content);
+        Ok(())
+    }
+    /// Reserve room for at least `additional` more elements, allocation failures
+    /// are reported instead of aborting
+    pub fn try_reserve(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        self.data.try_reserve(additional)
+    }
+    /// Number of elements the Binary Tree can hold without reallocating
+    pub fn capacity(&self) -> usize {
+        self.data.capacity()
+    }
+    /// Reserve room for at least `additional` more elements
+    pub fn reserve(&mut self, additional: usize) {
+        self.data.reserve(additional);
+    }
+    /// Give back the unused memory of the node arena
+    pub fn shrink_to_fit(&mut self) {
+        self.data.shrink_to_fit();
+    }
+    /// Build a balanced subtree from every sorted run of the elements in O(n),
+    /// then merge it into the tree. With `r` runs the cost is O(n log r)
+    fn extend_sorted<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        let mut iter = iter.into_iter();
+        let mut unsorted = None;
+        loop {
+            let mut run = unsorted.take().into_iter().chain(&mut iter);
+            unsorted = self.extend_sorted_prefix(&mut run, false);
+            if unsorted.is_none() {
+                break;
             }
-        };
+        }
     }
-    /// Remove a signgle element into the Binary Tree
-    pub fn remove(&mut self, value: &T) -> bool {
-        match self.root {
-            Some(mut index) => {
-                index = loop {
-                    if value == &self.data[index].content {
-                        break index;
-                    } else if value < &self.data[index].content {
-                        if self.data[index].left != NO_ENTRY {
-                            index = self.data[index].left;
-                        } else {
-                            break NO_ENTRY;
-                        }
-                    } else {
-                        if self.data[index].right != NO_ENTRY {
-                            index = self.data[index].right;
-                        } else {
-                            break NO_ENTRY;
-                        }
-                    }
-                };
-                if index == NO_ENTRY {
-                    false
-                } else {
-                    self.n -= 1;
-                    self.remove_find_case(
-                        index,
-                        #[cfg(debug_assertions)]
-                        false,
-                    );
-                    drop(self.swap_remove(index));
-                    true
+    /// Build a balanced subtree from the sorted head of `iter` in O(n), then
+    /// merge it into the tree. Give back the first element breaking the order,
+    /// with `strict` an element equal to the previous one breaks it too
+    fn extend_sorted_prefix<I: Iterator<Item = T>>(
+        &mut self,
+        iter: &mut I,
+        strict: bool,
+    ) -> Option<T> {
+        let offset = self.data.len();
+        let mut unsorted = None;
+        for content in iter {
+            if self.data.len() > offset {
+                let last = self.data[self.data.len() - 1].content();
+                if content < *last || (strict && content <= *last) {
+                    unsorted = Some(content);
+                    break;
+                }
+            }
+            self.data.push(Node::new(content, G::identity()));
+        }
+        let len = self.data.len() - offset;
+        if len != 0 {
+            self.n += len;
+            // Only the nodes of the deepest level are red
+            let root = self.build_sorted(offset, offset + len, NO_ENTRY, 0, len.ilog2());
+            set_black!(self.data[root]);
+            let root = self.union_nodes(self.root.unwrap_or(NO_ENTRY), root);
+            self.root = Some(root);
+        }
+        unsorted
+    }
+    /// Link the sorted nodes between `lo` and `hi` into a balanced subtree
+    fn build_sorted(
+        &mut self,
+        lo: usize,
+        hi: usize,
+        parent: usize,
+        depth: u32,
+        max_depth: u32,
+    ) -> usize {
+        if lo == hi {
+            return NO_ENTRY;
+        }
+        let mid = lo + (hi - lo) / 2;
+        let left = self.build_sorted(lo, mid, mid, depth + 1, max_depth);
+        let right = self.build_sorted(mid + 1, hi, mid, depth + 1, max_depth);
+        let node = &mut self.data[mid];
+        node.set_parent(parent);
+        node.set_left(left);
+        node.set_right(right);
+        node.set_color(if depth == max_depth && depth != 0 {
+            Color::Red
+        } else {
+            Color::Black
+        });
+        self.update_node(mid);
+        mid
+    }
+    /// Link a new node as a free child of `parent`, or as root, then rebalance
+    fn insert_at(&mut self, parent: usize, left: bool, content: T) -> usize {
+        self.n += 1;
+        let index = self.alloc_node(content);
+        if parent == NO_ENTRY {
+            self.set_as_root(index);
+            self.update_node(index);
+        } else {
+            if left {
+                debug_assert_eq!(self.data[parent].left(), NO_ENTRY);
+                self.data[parent].set_left(index);
+            } else {
+                debug_assert_eq!(self.data[parent].right(), NO_ENTRY);
+                self.data[parent].set_right(index);
+            }
+            self.data[index].set_parent(parent);
+            self.update_path(index);
+            self.insert_recurse(index);
+        }
+        index
+    }
+    /// Check if an element is present into the Binary Tree
+    pub fn contains<Q>(&self, value: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: core::cmp::PartialOrd + ?Sized,
+    {
+        self.find(value) != NO_ENTRY
+    }
+    /// Get a reference to the stored element equal to the value, the first one
+    /// inserted when several are equal
+    pub fn get<Q>(&self, value: &Q) -> Option<&T>
+    where
+        T: Borrow<Q>,
+        Q: core::cmp::PartialOrd + ?Sized,
+    {
+        let index = self.find_first(value);
+        (index != NO_ENTRY).then(|| self.data[index].content())
+    }
+    /// Remove the first element equal to the value, same as `remove_one`
+    pub fn remove<Q>(&mut self, value: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: core::cmp::PartialOrd + ?Sized,
+    {
+        self.remove_one(value)
+    }
+    /// Remove the first element equal to the value in iteration order
+    pub fn remove_one<Q>(&mut self, value: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: core::cmp::PartialOrd + ?Sized,
+    {
+        self.take(value).is_some()
+    }
+    /// Remove all the elements equal to the value, give back how many were removed
+    pub fn remove_all<Q>(&mut self, value: &Q) -> usize
+    where
+        T: Borrow<Q>,
+        Q: core::cmp::PartialOrd + ?Sized,
+    {
+        let mut count = 0;
+        while self.remove_one(value) {
+            count += 1;
+        }
+        count
+    }
+    /// Number of elements equal to the value
+    pub fn count<Q>(&self, value: &Q) -> usize
+    where
+        T: Borrow<Q>,
+        Q: core::cmp::PartialOrd + ?Sized,
+    {
+        let range = (Bound::Included(value), Bound::Included(value));
+        #[cfg(feature = "order-statistics")]
+        return self.count_range(range);
+        #[cfg(not(feature = "order-statistics"))]
+        self.range(range).count()
+    }
+    fn find<Q>(&self, value: &Q) -> usize
+    where
+        T: Borrow<Q>,
+        Q: core::cmp::PartialOrd + ?Sized,
+    {
+        self.find_by(|content| value.partial_cmp(content.borrow()))
+    }
+    /// Index of the first element equal to the value in iteration order
+    fn find_first<Q>(&self, value: &Q) -> usize
+    where
+        T: Borrow<Q>,
+        Q: core::cmp::PartialOrd + ?Sized,
+    {
+        let bound = Bound::Included(value);
+        let index = self.lower_bound_index(bound);
+        if index != NO_ENTRY && !after_end(bound, self.data[index].content().borrow()) {
+            index
+        } else {
+            NO_ENTRY
+        }
+    }
+    /// Remove the first element equal to the value from the Binary Tree and give it back
+    pub fn take<Q>(&mut self, value: &Q) -> Option<T>
+    where
+        T: Borrow<Q>,
+        Q: core::cmp::PartialOrd + ?Sized,
+    {
+        let index = self.find_first(value);
+        (index != NO_ENTRY).then(|| self.remove_index(index))
+    }
+    /// Get a reference to the smallest element
+    pub fn first(&self) -> Option<&T> {
+        let index = self.first_index();
+        (index != NO_ENTRY).then(|| self.data[index].content())
+    }
+    /// Get a reference to the greatest element
+    pub fn last(&self) -> Option<&T> {
+        let index = self.last_index();
+        (index != NO_ENTRY).then(|| self.data[index].content())
+    }
+    /// Remove the smallest element and give it back, the first one inserted
+    /// when several are equal
+    pub fn pop_first(&mut self) -> Option<T> {
+        let index = self.first_index();
+        (index != NO_ENTRY).then(|| self.remove_index(index))
+    }
+    /// Remove the greatest element and give it back, the last one inserted
+    /// when several are equal
+    pub fn pop_last(&mut self) -> Option<T> {
+        let index = self.last_index();
+        (index != NO_ENTRY).then(|| self.remove_index(index))
+    }
+    /// Insert an element into the Binary Tree, replacing and giving back an
+    /// equal element if one was already present, the first one inserted when
+    /// several are equal
+    pub fn replace(&mut self, content: T) -> Option<T> {
+        let index = self.find_first(&content);
+        if index == NO_ENTRY {
+            self.insert(content);
+            None
+        } else {
+            let old = core::mem::replace(self.data[index].content_mut(), content);
+            self.update_path(index);
+            Some(old)
+        }
+    }
+    /// Get the element at position `k` in sorted order
+    #[cfg(feature = "order-statistics")]
+    pub fn nth(&self, mut k: usize) -> Option<&T> {
+        let mut index = self.root.unwrap_or(NO_ENTRY);
+        while index != NO_ENTRY {
+            let left = self.size(self.data[index].left());
+            index = match k.cmp(&left) {
+                core::cmp::Ordering::Less => self.data[index].left(),
+                core::cmp::Ordering::Equal => return Some(self.data[index].content()),
+                core::cmp::Ordering::Greater => {
+                    k -= left + 1;
+                    self.data[index].right()
                 }
+            };
+        }
+        None
+    }
+    /// Count the elements lower than the value
+    #[cfg(feature = "order-statistics")]
+    pub fn rank<Q>(&self, value: &Q) -> usize
+    where
+        T: Borrow<Q>,
+        Q: core::cmp::PartialOrd + ?Sized,
+    {
+        self.count_while(|content| content.borrow() < value)
+    }
+    /// Count the elements contained into the range
+    #[cfg(feature = "order-statistics")]
+    pub fn count_range<Q, R>(&self, range: R) -> usize
+    where
+        T: Borrow<Q>,
+        Q: core::cmp::PartialOrd + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        let end = self.count_while(|content| !after_end(range.end_bound(), content.borrow()));
+        let start = self.count_while(|content| before_start(range.start_bound(), content.borrow()));
+        end.saturating_sub(start)
+    }
+    /// Combine the summaries of the elements contained into the range
+    pub fn fold_range<Q, R>(&self, range: R) -> G::Value
+    where
+        T: Borrow<Q>,
+        Q: core::cmp::PartialOrd + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        self.fold_range_recurse(
+            self.root.unwrap_or(NO_ENTRY),
+            range.start_bound(),
+            range.end_bound(),
+        )
+    }
+    fn fold_range_recurse<Q>(&self, x: usize, start: Bound<&Q>, end: Bound<&Q>) -> G::Value
+    where
+        T: Borrow<Q>,
+        Q: core::cmp::PartialOrd + ?Sized,
+    {
+        if x == NO_ENTRY {
+            return G::identity();
+        }
+        let node = &self.data[x];
+        if let (Bound::Unbounded, Bound::Unbounded) = (start, end) {
+            // The whole subtree is inside the range
+            node.summary.clone()
+        } else if before_start(start, node.content().borrow()) {
+            self.fold_range_recurse(node.right(), start, end)
+        } else if after_end(end, node.content().borrow()) {
+            self.fold_range_recurse(node.left(), start, end)
+        } else {
+            G::combine(
+                &self.fold_range_recurse(node.left(), start, Bound::Unbounded),
+                node.content(),
+                &self.fold_range_recurse(node.right(), Bound::Unbounded, end),
+            )
+        }
+    }
+    /// Count the elements of the sorted prefix for which `pred` holds
+    #[cfg(feature = "order-statistics")]
+    fn count_while<F: Fn(&T) -> bool>(&self, pred: F) -> usize {
+        let mut acc = 0;
+        let mut index = self.root.unwrap_or(NO_ENTRY);
+        while index != NO_ENTRY {
+            if pred(self.data[index].content()) {
+                acc += self.size(self.data[index].left()) + 1;
+                index = self.data[index].right();
+            } else {
+                index = self.data[index].left();
             }
-            None => false,
         }
+        acc
+    }
+    #[cfg(feature = "order-statistics")]
+    #[inline(always)]
+    fn size(&self, x: usize) -> usize {
+        if x == NO_ENTRY {
+            0
+        } else {
+            self.data[x].size()
+        }
+    }
+    /// Index of the smallest element
+    fn first_index(&self) -> usize {
+        let mut index = self.root.unwrap_or(NO_ENTRY);
+        if index != NO_ENTRY {
+            while self.data[index].left() != NO_ENTRY {
+                index = self.data[index].left();
+            }
+        }
+        index
+    }
+    /// Index of the greatest element
+    fn last_index(&self) -> usize {
+        let mut index = self.root.unwrap_or(NO_ENTRY);
+        if index != NO_ENTRY {
+            while self.data[index].right() != NO_ENTRY {
+                index = self.data[index].right();
+            }
+        }
+        index
+    }
+    /// Descent from root, `cmp` compares the searched value against the node content
+    fn find_by<F>(&self, cmp: F) -> usize
+    where
+        F: Fn(&T) -> Option<core::cmp::Ordering>,
+    {
+        use core::cmp::Ordering;
+        let mut index = self.root.unwrap_or(NO_ENTRY);
+        while index != NO_ENTRY {
+            index = match cmp(self.data[index].content()) {
+                Some(Ordering::Equal) => break,
+                Some(Ordering::Less) => self.data[index].left(),
+                _ => self.data[index].right(),
+            };
+        }
+        index
+    }
+    /// Unlink the node at `index` from the tree and give back its content
+    fn remove_index(&mut self, index: usize) -> T {
+        self.n -= 1;
+        self.remove_find_case(
+            index,
+            #[cfg(debug_assertions)]
+            false,
+        );
+        self.free_node(index)
     }
     /// Check if the tree is okay
     #[cfg(any(debug_assertions, test))]
     pub fn check_nodes(&self) {
         if let Some(index) = self.root {
-            assert_eq!(self.data[index].color as u64, Color::Black as u64);
+            assert_eq!(self.data[index].color() as u64, Color::Black as u64);
             let mut total_nodes = 0;
             self.check_nodes_recurse(index, 0, &mut total_nodes, Color::Black);
             assert_eq!(total_nodes, self.n);
         } else {
             assert_eq!(0, self.n);
         }
+        #[cfg(feature = "free-list")]
+        {
+            let (mut free, mut index) = (0, self.free);
+            while index != NO_ENTRY {
+                assert!(self.data[index].is_free());
+                free += 1;
+                index = self.data[index].right();
+            }
+            assert_eq!(free + self.n, self.data.len());
+        }
     }
-    /// Dump the entier Tree with Prefix rules
-    #[cfg(any(debug_assertions, test))]
+    /// Dump the entier Tree with Prefix rules on the standard output
+    #[cfg(any(test, all(feature = "std", debug_assertions)))]
     pub fn prefix_dump(&self)
     where
-        T: std::fmt::Debug,
+        T: core::fmt::Debug,
     {
-        if let Some(index) = self.root {
-            self.prefix_dump_recurse(index, 0);
+        let mut out = alloc::string::String::new();
+        self.prefix_dump_to(&mut out).unwrap();
+        print!("{}", out);
+    }
+    /// Dump the entier Tree with Prefix rules into `out`
+    #[cfg(any(debug_assertions, test))]
+    pub fn prefix_dump_to<W: core::fmt::Write>(&self, out: &mut W) -> core::fmt::Result
+    where
+        T: core::fmt::Debug,
+    {
+        match self.root {
+            Some(index) => self.prefix_dump_recurse(out, index, 0),
+            None => Ok(()),
         }
     }
     #[cfg(any(debug_assertions, test))]
-    fn prefix_dump_recurse(&self, x: usize, level: u32)
+    fn prefix_dump_recurse<W: core::fmt::Write>(
+        &self,
+        out: &mut W,
+        x: usize,
+        level: u32,
+    ) -> core::fmt::Result
     where
-        T: std::fmt::Debug,
+        T: core::fmt::Debug,
     {
-        if self.data[x].left != NO_ENTRY {
-            self.prefix_dump_recurse(self.data[x].left, level + 1);
+        if self.data[x].left() != NO_ENTRY {
+            self.prefix_dump_recurse(out, self.data[x].left(), level + 1)?;
         }
-        println!(
+        writeln!(
+            out,
             "lvl {} {:?} self: {} p: {} l: {} r: {} color: {:?}",
             level,
-            self.data[x].content,
+            self.data[x].content(),
             x,
-            self.data[x].parent,
-            self.data[x].left,
-            self.data[x].right,
-            self.data[x].color,
-        );
-        if self.data[x].right != NO_ENTRY {
-            self.prefix_dump_recurse(self.data[x].right, level + 1);
+            self.data[x].parent(),
+            self.data[x].left(),
+            self.data[x].right(),
+            self.data[x].color(),
+        )?;
+        if self.data[x].right() != NO_ENTRY {
+            self.prefix_dump_recurse(out, self.data[x].right(), level + 1)?;
         }
+        Ok(())
     }
     #[inline(always)]
     fn rotate_right(&mut self, low: usize, high: usize) {
-        debug_assert_eq!(self.data[low].parent, high);
-        self.data[low].parent = self.data[high].parent; // Assign new parents
-        self.data[high].parent = low;
-        self.data[high].left = self.data[low].right; // Move values
-        let left_index = self.data[high].left;
+        debug_assert_eq!(self.data[low].parent(), high);
+        let high_parent = self.data[high].parent();
+        self.data[low].set_parent(high_parent); // Assign new parents
+        self.data[high].set_parent(low);
+        let low_right = self.data[low].right();
+        self.data[high].set_left(low_right); // Move values
+        let left_index = self.data[high].left();
         if left_index != NO_ENTRY {
             // tell the son that I am his father
-            self.data[left_index].parent = high;
+            self.data[left_index].set_parent(high);
         }
-        self.data[low].right = high;
-        if self.data[low].parent == NO_ENTRY {
+        self.data[low].set_right(high);
+        if self.data[low].parent() == NO_ENTRY {
             // Root may be changed
             self.root = Some(low);
         } else {
             // Or juste change parent ref
-            let new_parent = self.data[low].parent;
+            let new_parent = self.data[low].parent();
             self.set_new_child(new_parent, high, low);
         }
+        self.update_node(high);
+        self.update_node(low);
     }
     #[inline(always)]
     fn rotate_left(&mut self, low: usize, high: usize) {
-        debug_assert_eq!(self.data[low].parent, high);
-        self.data[low].parent = self.data[high].parent; // Assign new parents
-        self.data[high].parent = low;
-        self.data[high].right = self.data[low].left; // Move values
-        let right_index = self.data[high].right;
+        debug_assert_eq!(self.data[low].parent(), high);
+        let high_parent = self.data[high].parent();
+        self.data[low].set_parent(high_parent); // Assign new parents
+        self.data[high].set_parent(low);
+        let low_left = self.data[low].left();
+        self.data[high].set_right(low_left); // Move values
+        let right_index = self.data[high].right();
         if right_index != NO_ENTRY {
             // tell the son that I am his father
-            self.data[right_index].parent = high;
+            self.data[right_index].set_parent(high);
         }
-        self.data[low].left = high;
-        if self.data[low].parent == NO_ENTRY {
+        self.data[low].set_left(high);
+        if self.data[low].parent() == NO_ENTRY {
             // Root may be changes
             self.root = Some(low);
         } else {
             // Or juste change parent ref
-            let new_parent = self.data[low].parent;
+            let new_parent = self.data[low].parent();
             self.set_new_child(new_parent, high, low);
         }
+        self.update_node(high);
+        self.update_node(low);
+    }
+    /// Refresh the informations a node keeps about its subtree
+    #[inline(always)]
+    fn update_node(&mut self, x: usize) {
+        #[cfg(feature = "order-statistics")]
+        {
+            let size = 1 + self.size(self.data[x].left()) + self.size(self.data[x].right());
+            self.data[x].set_size(size);
+        }
+        if Self::AUGMENTED {
+            let identity = G::identity();
+            let node = &self.data[x];
+            let left = match node.left() {
+                NO_ENTRY => &identity,
+                l => &self.data[l].summary,
+            };
+            let right = match node.right() {
+                NO_ENTRY => &identity,
+                r => &self.data[r].summary,
+            };
+            self.data[x].summary = G::combine(left, node.content(), right);
+        }
+    }
+    /// Refresh the subtree informations of a node and all its ancestors
+    fn update_path(&mut self, mut x: usize) {
+        if cfg!(feature = "order-statistics") || Self::AUGMENTED {
+            while x != NO_ENTRY {
+                self.update_node(x);
+                x = self.data[x].parent();
+            }
+        }
     }
     fn insert_recurse(&mut self, x: usize) {
-        let p = self.data[x].parent;
-        if is_red!(self.data[p]) {
-            if p != self.root.unwrap() {
-                let pp = self.data[p].parent;
-                let f = if p == self.data[pp].left {
-                    self.data[pp].right
-                } else {
-                    self.data[pp].left
-                };
-                if f == NO_ENTRY || is_black!(self.data[f]) {
-                    if p == self.data[pp].left {
-                        if x == self.data[p].left {
-                            self.rotate_right(p, pp);
-                            set_black!(self.data[p]);
-                            set_red!(self.data[pp]);
-                        } else {
-                            self.rotate_left(x, p);
-                            return self.insert_recurse(p);
-                        }
+        let p = self.data[x].parent();
+        if is_red!(self.data[p]) && p != self.root.unwrap() {
+            let pp = self.data[p].parent();
+            let f = if p == self.data[pp].left() {
+                self.data[pp].right()
+            } else {
+                self.data[pp].left()
+            };
+            if f == NO_ENTRY || is_black!(self.data[f]) {
+                if p == self.data[pp].left() {
+                    if x == self.data[p].left() {
+                        self.rotate_right(p, pp);
+                        set_black!(self.data[p]);
+                        set_red!(self.data[pp]);
                     } else {
-                        if x == self.data[p].right {
-                            self.rotate_left(p, pp);
-                            set_black!(self.data[p]);
-                            set_red!(self.data[pp]);
-                        } else {
-                            self.rotate_right(x, p);
-                            return self.insert_recurse(p);
-                        }
+                        self.rotate_left(x, p);
+                        self.insert_recurse(p)
                     }
                 } else {
-                    set_black!(self.data[p]);
-                    set_black!(self.data[f]);
-                    set_red!(self.data[pp]);
-                    self.data[self.root.unwrap()].color = Color::Black;
-                    if pp != self.root.unwrap() {
-                        return self.insert_recurse(pp);
+                    if x == self.data[p].right() {
+                        self.rotate_left(p, pp);
+                        set_black!(self.data[p]);
+                        set_red!(self.data[pp]);
+                    } else {
+                        self.rotate_right(x, p);
+                        self.insert_recurse(p)
                     }
                 }
+            } else {
+                set_black!(self.data[p]);
+                set_black!(self.data[f]);
+                set_red!(self.data[pp]);
+                self.data[self.root.unwrap()].set_color(Color::Black);
+                if pp != self.root.unwrap() {
+                    self.insert_recurse(pp)
+                }
+            }
+        }
+    }
+    /// Store a new unlinked node, into a free slot if there is one
+    fn alloc_node(&mut self, content: T) -> usize {
+        #[cfg(feature = "free-list")]
+        if self.free != NO_ENTRY {
+            let index = self.free;
+            self.free = self.data[index].right();
+            self.data[index] = Node::new(content, G::identity());
+            return index;
+        }
+        self.data.push(Node::new(content, G::identity()));
+        self.data.len() - 1
+    }
+    /// Give back the content of an unlinked node and release its slot
+    fn free_node(&mut self, index: usize) -> T {
+        self.take_node(index).into_content()
+    }
+    /// Move an unlinked node out of the arena
+    #[cfg(not(feature = "free-list"))]
+    fn take_node(&mut self, index: usize) -> Node<T, G::Value> {
+        self.swap_remove(index)
+    }
+    /// Move an unlinked node out of the arena, its slot goes into the free list
+    #[cfg(feature = "free-list")]
+    fn take_node(&mut self, index: usize) -> Node<T, G::Value> {
+        self.handles.forget(index);
+        let vacant = Node::vacant(self.free, G::identity());
+        self.free = index;
+        core::mem::replace(&mut self.data[index], vacant)
+    }
+    /// Move the nodes so that the arena is dense again, the free slots are
+    /// dropped. Handles stay valid. Call `shrink_to_fit` afterwards to give the
+    /// memory back
+    #[cfg(feature = "free-list")]
+    pub fn compact(&mut self) {
+        self.free = NO_ENTRY;
+        let mut i = 0;
+        while i < self.data.len() {
+            let last = self.data.len() - 1;
+            if self.data[last].is_free() {
+                self.swap_remove(last);
+            } else {
+                if self.data[i].is_free() {
+                    self.swap_remove(i);
+                }
+                i += 1;
             }
         }
     }
-    fn swap_remove(&mut self, index: usize) -> Node<T> {
+    fn swap_remove(&mut self, index: usize) -> Node<T, G::Value> {
+        self.handles.swap_remove(index, self.data.len());
         let node = self.data.swap_remove(index);
         if index < self.data.len() {
-            let p = self.data[index].parent;
-            let l = self.data[index].left;
-            let r = self.data[index].right;
+            let p = self.data[index].parent();
+            let l = self.data[index].left();
+            let r = self.data[index].right();
             if p != NO_ENTRY {
                 let old_len = self.data.len() + 1;
-                if self.data[p].left == (old_len - 1) {
-                    self.data[p].left = index;
-                } else if self.data[p].right == (old_len - 1) {
-                    self.data[p].right = index;
+                if self.data[p].left() == (old_len - 1) {
+                    self.data[p].set_left(index);
+                } else if self.data[p].right() == (old_len - 1) {
+                    self.data[p].set_right(index);
                 } else {
                     panic!("swap_remove woot ?");
                 }
@@ -348,20 +1169,44 @@ impl<T: std::cmp::PartialOrd, A: Allocator> RBBTree<T, A> {
                 self.root = Some(index);
             }
             if l != NO_ENTRY {
-                self.data[l].parent = index;
+                self.data[l].set_parent(index);
             }
             if r != NO_ENTRY {
-                self.data[r].parent = index;
+                self.data[r].set_parent(index);
             }
         }
         node
     }
+    /// Move the nodes into in order position inside the arena. The links are
+    /// not updated, so the tree is emptied
+    fn sort_data(&mut self) {
+        #[cfg(feature = "free-list")]
+        self.compact();
+        self.handles.invalidate();
+        let mut rank = vec![NO_ENTRY; self.data.len()];
+        if self.root.is_some() {
+            let mut index = self.first_index();
+            for r in 0..self.n {
+                rank[index] = r;
+                index = next_index(&self.data, index);
+            }
+        }
+        for i in 0..rank.len() {
+            while rank[i] != i {
+                let r = rank[i];
+                self.data.swap(i, r);
+                rank.swap(i, r);
+            }
+        }
+        self.root = None;
+        self.n = 0;
+    }
     #[inline(always)]
     fn set_new_child(&mut self, parent: usize, old_entry: usize, entry: usize) {
-        if old_entry == self.data[parent].left {
-            self.data[parent].left = entry;
-        } else if old_entry == self.data[parent].right {
-            self.data[parent].right = entry;
+        if old_entry == self.data[parent].left() {
+            self.data[parent].set_left(entry);
+        } else if old_entry == self.data[parent].right() {
+            self.data[parent].set_right(entry);
         } else {
             panic!("set_new_child woot ?");
         }
@@ -369,17 +1214,17 @@ impl<T: std::cmp::PartialOrd, A: Allocator> RBBTree<T, A> {
     #[inline(always)]
     fn set_as_root(&mut self, new_root: usize) {
         self.root = Some(new_root);
-        self.data[new_root].parent = NO_ENTRY;
+        self.data[new_root].set_parent(NO_ENTRY);
         set_black!(self.data[new_root]);
     }
     #[inline(always)]
     fn get_brother(&self, index: usize) -> usize {
-        debug_assert_ne!(self.data[index].parent, NO_ENTRY);
-        let parent = self.data[index].parent;
-        if index == self.data[parent].right {
-            self.data[parent].left
-        } else if index == self.data[parent].left {
-            self.data[parent].right
+        debug_assert_ne!(self.data[index].parent(), NO_ENTRY);
+        let parent = self.data[index].parent();
+        if index == self.data[parent].right() {
+            self.data[parent].left()
+        } else if index == self.data[parent].left() {
+            self.data[parent].right()
         } else {
             panic!("get_brother woot ?");
         }
@@ -387,17 +1232,17 @@ impl<T: std::cmp::PartialOrd, A: Allocator> RBBTree<T, A> {
     fn remove_modify_tree(&mut self, p: usize, f: usize) {
         debug_assert_ne!(p, NO_ENTRY);
         debug_assert_ne!(f, NO_ENTRY);
-        let p_color = self.data[p].color;
-        let f_color = self.data[f].color;
-        let sl = self.data[f].left;
+        let p_color = self.data[p].color();
+        let f_color = self.data[f].color();
+        let sl = self.data[f].left();
         let sl_color = if sl != NO_ENTRY {
-            self.data[sl].color
+            self.data[sl].color()
         } else {
             Color::Black
         };
-        let sr = self.data[f].right;
+        let sr = self.data[f].right();
         let sr_color = if sr != NO_ENTRY {
-            self.data[sr].color
+            self.data[sr].color()
         } else {
             Color::Black
         };
@@ -406,9 +1251,9 @@ impl<T: std::cmp::PartialOrd, A: Allocator> RBBTree<T, A> {
             Right,
         }
         use Symetric::*;
-        let symetric = if f == self.data[p].left {
+        let symetric = if f == self.data[p].left() {
             Left
-        } else if f == self.data[p].right {
+        } else if f == self.data[p].right() {
             Right
         } else {
             panic!("remove_modify_tree woot ?");
@@ -416,35 +1261,34 @@ impl<T: std::cmp::PartialOrd, A: Allocator> RBBTree<T, A> {
         match (symetric, f_color, sl_color, sr_color) {
             (_, Color::Black, Color::Black, Color::Black) => {
                 set_red!(self.data[f]);
-                let pp = self.data[p].parent;
+                let pp = self.data[p].parent();
                 if p_color as u64 == Color::Red as u64 {
-                    self.data[p].color = Color::Black;
+                    self.data[p].set_color(Color::Black);
                 } else if p_color as u64 == Color::Black as u64 && pp != NO_ENTRY {
                     let fp = self.get_brother(p);
                     self.remove_modify_tree(pp, fp);
-                } else {
                 }
             }
             (Left, Color::Black, Color::Red, _) => {
-                self.data[f].color = self.data[p].color;
+                self.data[f].set_color(p_color);
                 set_black!(self.data[p]);
                 set_black!(self.data[sl]);
                 self.rotate_right(f, p);
             }
             (Right, Color::Black, _, Color::Red) => {
-                self.data[f].color = self.data[p].color;
+                self.data[f].set_color(p_color);
                 set_black!(self.data[p]);
                 set_black!(self.data[sr]);
                 self.rotate_left(f, p);
             }
             (Left, Color::Black, _, Color::Red) => {
-                self.data[sr].color = self.data[p].color;
+                self.data[sr].set_color(p_color);
                 set_black!(self.data[p]);
                 self.rotate_left(sr, f);
                 self.rotate_right(sr, p);
             }
             (Right, Color::Black, Color::Red, _) => {
-                self.data[sl].color = self.data[p].color;
+                self.data[sl].set_color(p_color);
                 set_black!(self.data[p]);
                 self.rotate_right(sl, f);
                 self.rotate_left(sl, p);
@@ -464,9 +1308,9 @@ impl<T: std::cmp::PartialOrd, A: Allocator> RBBTree<T, A> {
         }
     }
     fn remove_find_case(&mut self, index: usize, #[cfg(debug_assertions)] recursive_call: bool) {
-        let p = self.data[index].parent;
-        let r = self.data[index].right;
-        let l = self.data[index].left;
+        let p = self.data[index].parent();
+        let r = self.data[index].right();
+        let l = self.data[index].left();
         let is_root = || -> bool { p == NO_ENTRY };
         let right_child_present = || -> bool { r != NO_ENTRY };
         let left_child_present = || -> bool { l != NO_ENTRY };
@@ -474,6 +1318,7 @@ impl<T: std::cmp::PartialOrd, A: Allocator> RBBTree<T, A> {
             if !is_root() {
                 let f = self.get_brother(index);
                 self.set_new_child(p, index, NO_ENTRY);
+                self.update_path(p);
                 if is_black!(self.data[index]) {
                     self.remove_modify_tree(p, f);
                 }
@@ -484,7 +1329,8 @@ impl<T: std::cmp::PartialOrd, A: Allocator> RBBTree<T, A> {
             if !is_root() {
                 let f = self.get_brother(index);
                 self.set_new_child(p, index, l);
-                self.data[l].parent = p;
+                self.data[l].set_parent(p);
+                self.update_path(p);
                 if is_black!(self.data[l]) {
                     self.remove_modify_tree(p, f);
                 } else {
@@ -497,7 +1343,8 @@ impl<T: std::cmp::PartialOrd, A: Allocator> RBBTree<T, A> {
             if !is_root() {
                 let f = self.get_brother(index);
                 self.set_new_child(p, index, r);
-                self.data[r].parent = p;
+                self.data[r].set_parent(p);
+                self.update_path(p);
                 if is_black!(self.data[r]) {
                     self.remove_modify_tree(p, f);
                 } else {
@@ -508,51 +1355,54 @@ impl<T: std::cmp::PartialOrd, A: Allocator> RBBTree<T, A> {
             }
         } else {
             #[cfg(debug_assertions)]
-            debug_assert_ne!(recursive_call, true);
+            debug_assert!(!recursive_call);
             let mut foreign_index = r; // Find right [left...] node
-            if self.data[foreign_index].left != NO_ENTRY {
-                while self.data[foreign_index].left != NO_ENTRY {
-                    foreign_index = self.data[foreign_index].left;
+            if self.data[foreign_index].left() != NO_ENTRY {
+                while self.data[foreign_index].left() != NO_ENTRY {
+                    foreign_index = self.data[foreign_index].left();
                 }
             }
 
-            let foreign_parent = self.data[foreign_index].parent;
-            self.data[index].parent = if foreign_parent != index {
+            let foreign_parent = self.data[foreign_index].parent();
+            let index_parent = if foreign_parent != index {
                 self.set_new_child(foreign_parent, foreign_index, index);
                 foreign_parent
             } else {
                 foreign_index
             };
-            self.data[foreign_index].parent = p;
+            self.data[index].set_parent(index_parent);
+            self.data[foreign_index].set_parent(p);
             if is_root() {
                 self.root = Some(foreign_index);
             } else {
                 self.set_new_child(p, index, foreign_index);
             }
 
-            self.data[foreign_index].left = l;
+            self.data[foreign_index].set_left(l);
             if left_child_present() {
-                self.data[l].parent = foreign_index;
+                self.data[l].set_parent(foreign_index);
             }
-            self.data[index].left = NO_ENTRY;
+            self.data[index].set_left(NO_ENTRY);
 
-            let index_color = self.data[index].color;
-            self.data[index].color = self.data[foreign_index].color;
-            self.data[foreign_index].color = index_color;
+            let index_color = self.data[index].color();
+            let foreign_color = self.data[foreign_index].color();
+            self.data[index].set_color(foreign_color);
+            self.data[foreign_index].set_color(index_color);
 
-            let foreign_right_child = self.data[foreign_index].right;
-            self.data[index].right = foreign_right_child;
+            let foreign_right_child = self.data[foreign_index].right();
+            self.data[index].set_right(foreign_right_child);
             if foreign_right_child != NO_ENTRY {
-                self.data[foreign_right_child].parent = index;
+                self.data[foreign_right_child].set_parent(index);
             }
-            self.data[foreign_index].right = if r != foreign_index {
+            let foreign_right = if r != foreign_index {
                 if right_child_present() {
-                    self.data[r].parent = foreign_index;
+                    self.data[r].set_parent(foreign_index);
                 }
                 r
             } else {
                 index
             };
+            self.data[foreign_index].set_right(foreign_right);
             self.remove_find_case(
                 index,
                 #[cfg(debug_assertions)]
@@ -569,58 +1419,236 @@ impl<T: std::cmp::PartialOrd, A: Allocator> RBBTree<T, A> {
         color: Color,
     ) -> usize {
         let x_ref = &self.data[x];
-        let parent = x_ref.parent;
-        if parent != NO_ENTRY {
-            if x != self.data[parent].left && x != self.data[parent].right {
-                panic!("Orphelan Node");
-            }
+        let parent = x_ref.parent();
+        if parent != NO_ENTRY && x != self.data[parent].left() && x != self.data[parent].right() {
+            panic!("Orphelan Node");
         }
-        if color as u64 == Color::Red as u64 && x_ref.color as u64 == Color::Red as u64 {
+        if color as u64 == Color::Red as u64 && x_ref.color() as u64 == Color::Red as u64 {
             panic!("A red node follow a red node: bl_lvl {}", acc);
         }
-        let color = x_ref.color;
+        let color = x_ref.color();
         if let Color::Black = color {
             black_nodes += 1;
         }
         *acc += 1;
-        let black_left = if x_ref.left != NO_ENTRY {
-            self.check_nodes_recurse(x_ref.left, black_nodes, acc, color)
+        #[cfg(feature = "order-statistics")]
+        let first = *acc;
+        let black_left = if x_ref.left() != NO_ENTRY {
+            self.check_nodes_recurse(x_ref.left(), black_nodes, acc, color)
         } else {
             black_nodes
         };
-        let black_right = if x_ref.right != NO_ENTRY {
-            self.check_nodes_recurse(x_ref.right, black_nodes, acc, color)
+        let black_right = if x_ref.right() != NO_ENTRY {
+            self.check_nodes_recurse(x_ref.right(), black_nodes, acc, color)
         } else {
             black_nodes
         };
         assert_eq!(black_left, black_right);
+        #[cfg(feature = "order-statistics")]
+        assert_eq!(x_ref.size(), *acc - first + 1);
         black_left
     }
 }
 
-impl<'a, T, A: Allocator> Iterator for RBBTreeIterator<'a, T, A> {
+impl<'a, T, A: Allocator, S> Iterator for RBBTreeIterator<'a, T, A, S> {
     // we will be counting with usize
     type Item = &'a T;
 
     // next() is the only required method
     fn next(&mut self) -> Option<Self::Item> {
-        while !self.stack.is_empty() || self.x != NO_ENTRY {
-            if self.x != NO_ENTRY {
-                self.stack.push(self.x);
-                self.x = self.data[self.x].left;
-            } else {
-                self.x = self.stack.pop().unwrap();
-                let content = &self.data[self.x].content;
-                self.x = self.data[self.x].right;
-                return Some(content);
+        if self.front == NO_ENTRY {
+            return None;
+        }
+        let x = self.front;
+        if x == self.back {
+            // Both ends have met
+            self.front = NO_ENTRY;
+            self.back = NO_ENTRY;
+        } else {
+            self.front = next_index(self.data, x);
+        }
+        Some(self.data[x].content())
+    }
+}
+
+impl<'a, T, A: Allocator, S> DoubleEndedIterator for RBBTreeIterator<'a, T, A, S> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.back == NO_ENTRY {
+            return None;
+        }
+        let x = self.back;
+        if x == self.front {
+            // Both ends have met
+            self.front = NO_ENTRY;
+            self.back = NO_ENTRY;
+        } else {
+            self.back = prev_index(self.data, x);
+        }
+        Some(self.data[x].content())
+    }
+}
+
+impl<'a, T, A: Allocator, S> Iterator for RBBTreeRange<'a, T, A, S> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()
+    }
+}
+
+impl<'a, T, A: Allocator, S> DoubleEndedIterator for RBBTreeRange<'a, T, A, S> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back()
+    }
+}
+
+impl<T: core::cmp::PartialOrd, A: Allocator, G: Augment<T>> IntoIterator for RBBTree<T, A, G> {
+    type Item = T;
+    type IntoIter = RBBTreeIntoIter<T, A, G::Value>;
+
+    fn into_iter(mut self) -> Self::IntoIter {
+        self.sort_data();
+        RBBTreeIntoIter {
+            inner: self.data.into_iter(),
+            #[cfg(not(nightly))]
+            phantom: core::marker::PhantomData,
+        }
+    }
+}
+
+impl<'a, T: core::cmp::PartialOrd, A: Allocator, G: Augment<T>> IntoIterator
+    for &'a RBBTree<T, A, G>
+{
+    type Item = &'a T;
+    type IntoIter = RBBTreeIterator<'a, T, A, G::Value>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<T: core::cmp::PartialOrd, A: Allocator, G: Augment<T>> Extend<T> for RBBTree<T, A, G> {
+    /// Every sorted run of the batch is built in O(n) then merged in the tree.
+    /// The batch is never sorted, incomparable elements do not form a total order
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        self.extend_sorted(iter);
+    }
+}
+
+impl<'a, T: core::cmp::PartialOrd + Copy + 'a, A: Allocator, G: Augment<T>> Extend<&'a T>
+    for RBBTree<T, A, G>
+{
+    fn extend<I: IntoIterator<Item = &'a T>>(&mut self, iter: I) {
+        self.extend(iter.into_iter().copied());
+    }
+}
+
+impl<T: core::cmp::PartialOrd, G: Augment<T>> FromIterator<T> for RBBTree<T, Global, G> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut tree = Self::default();
+        tree.extend(iter);
+        tree
+    }
+}
+
+impl<T: core::cmp::PartialOrd, const N: usize> From<[T; N]> for RBBTree<T> {
+    fn from(array: [T; N]) -> Self {
+        Self::from_iter(array)
+    }
+}
+
+impl<T, A: Allocator, S> Iterator for RBBTreeIntoIter<T, A, S> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|node| node.into_content())
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<T, A: Allocator, S> DoubleEndedIterator for RBBTreeIntoIter<T, A, S> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(|node| node.into_content())
+    }
+}
+
+impl<T, A: Allocator, S> ExactSizeIterator for RBBTreeIntoIter<T, A, S> {}
+
+impl<'a, T, A: Allocator, S> Iterator for RBBTreeDrain<'a, T, A, S> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|node| node.into_content())
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, T, A: Allocator, S> DoubleEndedIterator for RBBTreeDrain<'a, T, A, S> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(|node| node.into_content())
+    }
+}
+
+impl<'a, T, A: Allocator, S> ExactSizeIterator for RBBTreeDrain<'a, T, A, S> {}
+
+/// In order successor of a node, found with the parent links
+fn next_index<T, S>(data: &[Node<T, S>], mut x: usize) -> usize {
+    if data[x].right() != NO_ENTRY {
+        x = data[x].right();
+        while data[x].left() != NO_ENTRY {
+            x = data[x].left();
+        }
+        x
+    } else {
+        loop {
+            let p = data[x].parent();
+            if p == NO_ENTRY || data[p].left() == x {
+                break p;
             }
+            x = p;
         }
-        return None;
     }
 }
 
-impl<T: std::cmp::PartialOrd, A: Allocator> Drop for RBBTree<T, A> {
-    fn drop(&mut self) {}
+/// In order predecessor of a node, found with the parent links
+fn prev_index<T, S>(data: &[Node<T, S>], mut x: usize) -> usize {
+    if data[x].left() != NO_ENTRY {
+        x = data[x].left();
+        while data[x].right() != NO_ENTRY {
+            x = data[x].right();
+        }
+        x
+    } else {
+        loop {
+            let p = data[x].parent();
+            if p == NO_ENTRY || data[p].right() == x {
+                break p;
+            }
+            x = p;
+        }
+    }
+}
+
+/// Check if the value is located before the start of a range
+fn before_start<Q: core::cmp::PartialOrd + ?Sized>(bound: Bound<&Q>, value: &Q) -> bool {
+    match bound {
+        Bound::Included(start) => value < start,
+        Bound::Excluded(start) => value <= start,
+        Bound::Unbounded => false,
+    }
+}
+
+/// Check if the value is located after the end of a range
+fn after_end<Q: core::cmp::PartialOrd + ?Sized>(bound: Bound<&Q>, value: &Q) -> bool {
+    match bound {
+        Bound::Included(end) => value > end,
+        Bound::Excluded(end) => value >= end,
+        Bound::Unbounded => false,
+    }
 }
 
 /**
@@ -631,10 +1659,12 @@ RELEASE
 RUST_BACKTRACE=1 RUSTFLAGS=-Zsanitizer=address cargo test --release -Zbuild-std --target x86_64-unknown-linux-gnu
 */
 #[cfg(test)]
+#[allow(clippy::redundant_closure, clippy::bool_assert_comparison)]
 mod test {
     use super::RBBTree;
     use rand::distributions::Standard;
     use rand::prelude::*;
+    use std::ops::{Bound, RangeBounds};
     #[test]
     fn simple() {
         let v = vec![
@@ -672,6 +1702,363 @@ mod test {
         }
     }
     #[test]
+    fn borrowed_lookup() {
+        let mut rnb = RBBTree::new();
+        for word in ["red", "black", "binary", "tree"] {
+            rnb.insert(String::from(word));
+        }
+        assert!(rnb.contains("binary"));
+        assert!(!rnb.contains("green"));
+        assert_eq!(rnb.get("tree").map(String::as_str), Some("tree"));
+        assert_eq!(rnb.get("leaf"), None);
+        assert!(rnb.remove("red"));
+        assert!(!rnb.contains("red"));
+        rnb.check_nodes();
+    }
+    #[test]
+    fn range() {
+        let mut rng = rand::thread_rng();
+        let mut v: Vec<u32> = (0..256).map(|_| rng.gen_range(0..128)).collect();
+        let mut rnb = RBBTree::new();
+        for val in v.iter() {
+            rnb.insert(*val);
+        }
+        v.sort();
+        let expect = |f: &dyn Fn(&u32) -> bool| v.iter().filter(|x| f(x)).collect::<Vec<_>>();
+        for _i in 0..64 {
+            let a = rng.gen_range(0..140);
+            let b = rng.gen_range(0..140);
+            assert_eq!(
+                rnb.range(a..b).collect::<Vec<_>>(),
+                expect(&|x| (a..b).contains(x))
+            );
+            assert_eq!(
+                rnb.range(a..=b).collect::<Vec<_>>(),
+                expect(&|x| (a..=b).contains(x))
+            );
+            assert_eq!(
+                rnb.range(a..).collect::<Vec<_>>(),
+                expect(&|x| (a..).contains(x))
+            );
+            assert_eq!(
+                rnb.range(..b).collect::<Vec<_>>(),
+                expect(&|x| (..b).contains(x))
+            );
+            let bounds = (Bound::Excluded(a), Bound::Included(b));
+            assert_eq!(
+                rnb.range(bounds).collect::<Vec<_>>(),
+                expect(&|x| bounds.contains(x))
+            );
+        }
+        assert_eq!(rnb.range(..).count(), v.len());
+    }
+    #[test]
+    fn double_ended() {
+        let mut rnb = RBBTree::new();
+        for val in [5, 3, 8, 1, 4, 7, 9, 2, 6, 0] {
+            rnb.insert(val);
+        }
+        assert!(rnb.iter().rev().copied().eq((0..10).rev()));
+        assert!(rnb.range(2..=7).rev().copied().eq((2..=7).rev()));
+        let mut iter = rnb.iter();
+        let mut acc = Vec::new();
+        while let (Some(a), Some(b)) = (iter.next(), iter.next_back()) {
+            acc.push((*a, *b));
+        }
+        assert_eq!(acc, [(0, 9), (1, 8), (2, 7), (3, 6), (4, 5)]);
+        assert_eq!(iter.next(), None);
+        let mut range = rnb.range(3..6);
+        assert_eq!(range.next_back(), Some(&5));
+        assert_eq!(range.next(), Some(&3));
+        assert_eq!(range.next(), Some(&4));
+        assert_eq!(range.next_back(), None);
+        assert_eq!(
+            rnb.range((Bound::Included(6), Bound::Excluded(3)))
+                .next_back(),
+            None
+        );
+    }
+    #[test]
+    fn owning_iterators() {
+        let mut rnb = RBBTree::new();
+        for i in 0..128 {
+            rnb.insert(format!("{:03}", (i * 37) % 128));
+        }
+        rnb.remove("017");
+        rnb.remove("042");
+        let expected: Vec<String> = (0..128)
+            .filter(|i| *i != 17 && *i != 42)
+            .map(|i| format!("{:03}", i))
+            .collect();
+        let drained: Vec<String> = rnb.drain().collect();
+        assert_eq!(drained, expected);
+        assert_eq!(rnb.iter().next(), None);
+        rnb.check_nodes();
+        for val in expected.iter() {
+            rnb.insert(val.clone());
+        }
+        rnb.check_nodes();
+        assert!(rnb.into_iter().rev().eq(expected.into_iter().rev()));
+    }
+    #[test]
+    fn take_replace() {
+        #[derive(Debug, PartialEq)]
+        struct Payload(u32, &'static str);
+        impl PartialOrd for Payload {
+            fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+                self.0.partial_cmp(&other.0)
+            }
+        }
+        let mut rnb = RBBTree::new();
+        for (i, name) in ["zero", "one", "two", "three"].into_iter().enumerate() {
+            assert_eq!(rnb.replace(Payload(i as u32, name)), None);
+        }
+        assert_eq!(rnb.replace(Payload(2, "deux")), Some(Payload(2, "two")));
+        assert_eq!(rnb.take(&Payload(2, "")), Some(Payload(2, "deux")));
+        assert_eq!(rnb.take(&Payload(2, "")), None);
+        assert_eq!(rnb.take(&Payload(0, "")), Some(Payload(0, "zero")));
+        rnb.check_nodes();
+        assert!(rnb.iter().map(|p| p.1).eq(["one", "three"]));
+    }
+    #[test]
+    fn priority_queue() {
+        let mut rng = rand::thread_rng();
+        let mut rnb = RBBTree::new();
+        let mut expected: Vec<u32> = (0..256).map(|_| rng.gen_range(0..64)).collect();
+        for val in expected.iter() {
+            rnb.insert(*val);
+        }
+        expected.sort();
+        assert_eq!(rnb.first(), expected.first());
+        assert_eq!(rnb.last(), expected.last());
+        while !expected.is_empty() {
+            let popped = if rng.gen() {
+                (rnb.pop_first(), Some(expected.remove(0)))
+            } else {
+                (rnb.pop_last(), expected.pop())
+            };
+            assert_eq!(popped.0, popped.1);
+            assert_eq!(rnb.first(), expected.first());
+            assert_eq!(rnb.last(), expected.last());
+        }
+        rnb.check_nodes();
+        assert_eq!(rnb.pop_first(), None);
+        assert_eq!(rnb.pop_last(), None);
+    }
+    #[test]
+    fn multiset() {
+        #[derive(Debug)]
+        struct Tagged(u32, usize);
+        impl PartialEq for Tagged {
+            fn eq(&self, other: &Self) -> bool {
+                self.0 == other.0
+            }
+        }
+        impl PartialOrd for Tagged {
+            fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+                self.0.partial_cmp(&other.0)
+            }
+        }
+        let mut rnb = RBBTree::new();
+        for i in 0..64 {
+            rnb.insert(Tagged(i as u32 % 4, i));
+        }
+        rnb.extend((64..128).map(|i| Tagged(i as u32 % 4, i)));
+        let mut other: RBBTree<Tagged> = (128..160).map(|i| Tagged(i as u32 % 4, i)).collect();
+        rnb.append(&mut other);
+        rnb.check_nodes();
+        assert_eq!(rnb.count(&Tagged(1, 0)), 40);
+        // Equal elements iterate in insertion order
+        assert!(rnb
+            .iter()
+            .map(|t| t.1)
+            .eq((0..4).flat_map(|k| (k..160).step_by(4))));
+        assert_eq!(rnb.get(&Tagged(2, 0)).map(|t| t.1), Some(2));
+        assert_eq!(rnb.replace(Tagged(2, 1000)).map(|t| t.1), Some(2));
+        assert_eq!(rnb.get(&Tagged(2, 0)).map(|t| t.1), Some(1000));
+        assert_eq!(rnb.take(&Tagged(2, 0)).map(|t| t.1), Some(1000));
+        assert_eq!(rnb.get(&Tagged(2, 0)).map(|t| t.1), Some(6));
+        assert_eq!(rnb.remove_all(&Tagged(3, 0)), 40);
+        assert_eq!(rnb.count(&Tagged(3, 0)), 0);
+        assert!(rnb.remove_one(&Tagged(0, 0)));
+        assert_eq!(rnb.count(&Tagged(0, 0)), 39);
+        let mut first = RBBTree::new();
+        first.insert(Tagged(1, 1000));
+        first.append(&mut rnb);
+        first.check_nodes();
+        assert_eq!(first.iter().find(|t| t.0 == 1).map(|t| t.1), Some(1000));
+    }
+    #[cfg(feature = "order-statistics")]
+    #[test]
+    fn order_statistics() {
+        let mut rng = rand::thread_rng();
+        let mut v: Vec<u32> = (0..512).map(|_| rng.gen_range(0..256)).collect();
+        let mut rnb = RBBTree::new();
+        for val in v.iter() {
+            rnb.insert(*val);
+            rnb.check_nodes();
+        }
+        v.shuffle(&mut rng);
+        for val in v.drain(256..) {
+            assert!(rnb.remove(&val));
+            rnb.check_nodes();
+        }
+        v.sort();
+        for (k, val) in v.iter().enumerate() {
+            assert_eq!(rnb.nth(k), Some(val));
+        }
+        assert_eq!(rnb.nth(v.len()), None);
+        for x in 0..260 {
+            assert_eq!(rnb.rank(&x), v.iter().filter(|e| **e < x).count());
+            let r = x..x + 17;
+            assert_eq!(
+                rnb.count_range(r.clone()),
+                v.iter().filter(|e| r.contains(e)).count()
+            );
+        }
+        assert_eq!(rnb.count_range(..), v.len());
+    }
+    #[test]
+    fn augmentation() {
+        use super::{Augment, Global};
+        // Not commutative, the summary is the sorted list of the subtree elements
+        struct Collect;
+        impl Augment<u32> for Collect {
+            type Value = Vec<u32>;
+            fn identity() -> Self::Value {
+                Vec::new()
+            }
+            fn combine(left: &Self::Value, content: &u32, right: &Self::Value) -> Self::Value {
+                [&left[..], &[*content], &right[..]].concat()
+            }
+        }
+        struct Sum;
+        impl Augment<u32> for Sum {
+            type Value = u64;
+            fn identity() -> Self::Value {
+                0
+            }
+            fn combine(left: &Self::Value, content: &u32, right: &Self::Value) -> Self::Value {
+                left + *content as u64 + right
+            }
+        }
+        let mut rng = rand::thread_rng();
+        let mut v: Vec<u32> = (0..128).map(|_| rng.gen_range(0..64)).collect();
+        let mut collect: RBBTree<u32, Global, Collect> = RBBTree::default();
+        let mut sum: RBBTree<u32, Global, Sum> = RBBTree::default();
+        for val in v.iter() {
+            collect.insert(*val);
+            sum.insert(*val);
+        }
+        v.shuffle(&mut rng);
+        for val in v.drain(64..) {
+            assert!(collect.remove(&val));
+            assert!(sum.remove(&val));
+        }
+        collect.check_nodes();
+        v.sort();
+        for _i in 0..64 {
+            let a = rng.gen_range(0..70);
+            let b = rng.gen_range(a..70);
+            let expected: Vec<u32> = v.iter().copied().filter(|x| (a..=b).contains(x)).collect();
+            assert_eq!(
+                sum.fold_range(a..=b),
+                expected.iter().map(|x| *x as u64).sum::<u64>()
+            );
+            assert_eq!(collect.fold_range(a..=b), expected);
+        }
+        assert_eq!(collect.fold_range(..), v);
+    }
+    #[test]
+    fn from_sorted() {
+        for n in 0..300 {
+            let rnb = RBBTree::from_sorted_iter(0..n);
+            rnb.check_nodes();
+            assert!(rnb.iter().copied().eq(0..n));
+        }
+        let rnb = RBBTree::from_sorted_iter((0..100).chain((0..100).rev()));
+        rnb.check_nodes();
+        assert!(rnb.iter().copied().eq((0..100).flat_map(|x| [x, x])));
+    }
+    #[test]
+    fn collect_and_extend() {
+        let mut rng = rand::thread_rng();
+        let mut v: Vec<u32> = (0..512).map(|_| rng.gen_range(0..256)).collect();
+        let mut rnb: RBBTree<u32> = v.iter().copied().collect();
+        rnb.check_nodes();
+        for batch in [3, 40, 600] {
+            let more: Vec<u32> = (0..batch).map(|_| rng.gen_range(0..256)).collect();
+            rnb.extend(&more);
+            rnb.check_nodes();
+            v.extend(more);
+        }
+        v.sort();
+        assert!(rnb.iter().eq(v.iter()));
+        let rnb = RBBTree::from([3, 1, 2]);
+        assert!(rnb.iter().copied().eq(1..4));
+    }
+    #[test]
+    fn collect_incomparable() {
+        let mut rng = rand::thread_rng();
+        for len in [16, 100, 5000] {
+            let v: Vec<f64> = (0..len)
+                .map(|_| {
+                    if rng.gen_bool(0.3) {
+                        f64::NAN
+                    } else {
+                        rng.gen()
+                    }
+                })
+                .collect();
+            let rnb: RBBTree<f64> = v.iter().copied().collect();
+            rnb.check_nodes();
+            assert_eq!(rnb.iter().count(), len);
+        }
+    }
+    #[test]
+    fn collect_runs() {
+        use std::cell::Cell;
+        // Count the comparisons to tell the bulk build from one insertion per element
+        struct Counted<'a>(u32, &'a Cell<usize>);
+        impl PartialEq for Counted<'_> {
+            fn eq(&self, other: &Self) -> bool {
+                self.1.set(self.1.get() + 1);
+                self.0 == other.0
+            }
+        }
+        impl PartialOrd for Counted<'_> {
+            fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+                self.1.set(self.1.get() + 1);
+                self.0.partial_cmp(&other.0)
+            }
+        }
+        let mut rng = rand::thread_rng();
+        let comparisons = Cell::new(0);
+        // 8 sorted runs of shuffled values
+        let mut v: Vec<u32> = (0..4096).collect();
+        v.shuffle(&mut rng);
+        for run in v.chunks_mut(512) {
+            run.sort();
+        }
+        let mut rnb = RBBTree::new();
+        for val in &v {
+            rnb.insert(Counted(*val, &comparisons));
+        }
+        let one_by_one = comparisons.replace(0);
+        let rnb: RBBTree<Counted> = v.iter().map(|val| Counted(*val, &comparisons)).collect();
+        assert!(
+            comparisons.get() * 2 < one_by_one,
+            "{} comparisons, {one_by_one} one by one",
+            comparisons.get()
+        );
+        rnb.check_nodes();
+        assert!(rnb.iter().map(|c| c.0).eq(0..4096));
+        v.shuffle(&mut rng);
+        let rnb: RBBTree<u32> = v.iter().copied().collect();
+        rnb.check_nodes();
+        assert!(rnb.iter().copied().eq(0..4096));
+    }
+    #[test]
     fn multiple() {
         make_multiple_test(|| RBBTree::new(), &12.43);
     }
@@ -698,6 +2085,134 @@ mod test {
         }
         make_multiple_test(|| RBBTree::new_in(&CustomAllocator {}), &12.43);
     }
+    #[test]
+    fn fallible_allocation() {
+        let mut rnb = RBBTree::new();
+        for val in 0..64 {
+            assert!(rnb.try_insert(val).is_ok());
+        }
+        assert!(rnb.try_reserve(usize::MAX).is_err());
+        assert!(rnb.try_reserve(64).is_ok());
+        rnb.check_nodes();
+        assert!(rnb.iter().copied().eq(0..64));
+        #[cfg(feature = "free-list")]
+        {
+            // The arena is full but the freed slots are reused, nothing is reserved
+            rnb.shrink_to_fit();
+            let capacity = rnb.capacity();
+            assert!(rnb.remove(&0) && rnb.remove(&1));
+            assert!(rnb.try_insert(64).is_ok() && rnb.try_insert(65).is_ok());
+            assert_eq!(rnb.capacity(), capacity);
+            rnb.check_nodes();
+            assert!(rnb.iter().copied().eq(2..66));
+        }
+    }
+    #[cfg(all(feature = "compact", not(feature = "cache-aligned")))]
+    #[test]
+    fn compact_layout() {
+        // A free-list arena wraps the content into an `Option`
+        let content = if cfg!(feature = "free-list") { 8 } else { 4 };
+        let links = 3 * 4
+            + if cfg!(feature = "order-statistics") {
+                4
+            } else {
+                0
+            };
+        assert_eq!(std::mem::size_of::<super::Node<u32>>(), content + links);
+    }
+    #[test]
+    fn capacity() {
+        let mut rnb = RBBTree::with_capacity(256);
+        assert!(rnb.capacity() >= 256);
+        for val in 0..256 {
+            rnb.insert(val);
+        }
+        for val in 0..192 {
+            assert!(rnb.remove(&val));
+        }
+        #[cfg(feature = "free-list")]
+        rnb.compact();
+        rnb.shrink_to_fit();
+        assert!(rnb.capacity() < 256);
+        rnb.reserve(512);
+        assert!(rnb.capacity() >= 576);
+        rnb.check_nodes();
+        assert!(rnb.iter().copied().eq(192..256));
+    }
+    #[test]
+    fn free_list() {
+        let mut rnb = RBBTree::new();
+        let handles: Vec<_> = (0..256u32).map(|val| rnb.insert_with_handle(val)).collect();
+        for val in (0..256).step_by(2) {
+            assert!(rnb.remove(&val));
+        }
+        for val in 256..320 {
+            rnb.insert(val);
+        }
+        rnb.check_nodes();
+        // The freed slots were reused, the arena did not grow
+        #[cfg(feature = "free-list")]
+        assert_eq!(rnb.data.len(), 256);
+        #[cfg(feature = "free-list")]
+        rnb.compact();
+        rnb.check_nodes();
+        assert_eq!(rnb.data.len(), 192);
+        for (val, handle) in handles.into_iter().enumerate() {
+            let expected = (val % 2 == 1).then_some(val as u32);
+            assert_eq!(rnb.get_by_handle(handle), expected.as_ref());
+        }
+        assert!(rnb.iter().copied().eq((1..256).step_by(2).chain(256..320)));
+    }
+    #[cfg(nightly)]
+    #[test]
+    fn fallible_custom_alloc() {
+        use std::alloc::{AllocError, Allocator, Global, Layout};
+        use std::cell::Cell;
+        use std::ptr::NonNull;
+
+        /// Refuse to hand out more than `left` bytes
+        struct CappedAllocator {
+            left: Cell<usize>,
+        }
+
+        unsafe impl Allocator for CappedAllocator {
+            fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+                if layout.size() > self.left.get() {
+                    return Err(AllocError);
+                }
+                self.left.set(self.left.get() - layout.size());
+                Global.allocate(layout)
+            }
+            unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+                self.left.set(self.left.get() + layout.size());
+                Global.deallocate(ptr, layout)
+            }
+        }
+        let alloc = CappedAllocator {
+            left: Cell::new(4096),
+        };
+        let mut rnb = RBBTree::new_in(&alloc);
+        let mut inserted = 0;
+        let refused = loop {
+            match rnb.try_insert(inserted) {
+                Ok(()) => inserted += 1,
+                Err((val, _)) => break val,
+            }
+        };
+        assert_eq!(refused, inserted);
+        rnb.check_nodes();
+        assert!(rnb.iter().copied().eq(0..inserted));
+        assert!(rnb.try_reserve(1).is_err());
+        #[cfg(feature = "free-list")]
+        {
+            // The freed slot is reused even though the arena can not grow
+            assert!(rnb.remove(&0));
+            assert!(rnb.try_insert(inserted).is_ok());
+            assert!(rnb.try_insert(inserted + 1).is_err());
+            rnb.check_nodes();
+            assert!(rnb.iter().copied().eq(1..inserted + 1));
+        }
+    }
     fn make_multiple_test<F, T, A>(gen: F, bad_value: &T)
     where
         F: Fn() -> RBBTree<T, A>,
@@ -0,0 +1,237 @@
+//! Key/Value Map based on the Red and Black Binary Tree
+use super::{Allocator, Global, RBBTree, RBBTreeIterator, NO_ENTRY};
+use core::borrow::Borrow;
+
+/// Node content of the map, only the key is used for ordering
+struct MapEntry<K, V> {
+    key: K,
+    value: V,
+}
+
+impl<K: PartialEq, V> PartialEq for MapEntry<K, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key
+    }
+}
+impl<K: PartialOrd, V> PartialOrd for MapEntry<K, V> {
+    fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
+        self.key.partial_cmp(&other.key)
+    }
+}
+
+/// Map Structure
+pub struct RBBMap<K: PartialOrd, V, A: Allocator = Global> {
+    tree: RBBTree<MapEntry<K, V>, A>,
+}
+
+/// Iterator over the entries of a Map
+pub struct RBBMapIterator<'a, K, V, A: Allocator = Global> {
+    inner: RBBTreeIterator<'a, MapEntry<K, V>, A>,
+}
+
+/// Iterator over the keys of a Map
+pub struct RBBMapKeys<'a, K, V, A: Allocator = Global> {
+    inner: RBBTreeIterator<'a, MapEntry<K, V>, A>,
+}
+
+/// Iterator over the values of a Map
+pub struct RBBMapValues<'a, K, V, A: Allocator = Global> {
+    inner: RBBTreeIterator<'a, MapEntry<K, V>, A>,
+}
+
+impl<K: PartialOrd, V> RBBMap<K, V> {
+    /// Create a new Map
+    pub fn new() -> Self {
+        Self {
+            tree: RBBTree::new(),
+        }
+    }
+}
+impl<K: PartialOrd, V> Default for RBBMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl<K: PartialOrd, V, A: Allocator> RBBMap<K, V, A> {
+    #[cfg(nightly)]
+    /// Create a new Map with Custom Allocator
+    pub fn new_in(alloc: A) -> Self {
+        Self {
+            tree: RBBTree::new_in(alloc),
+        }
+    }
+    /// Number of entries into the Map
+    pub fn len(&self) -> usize {
+        self.tree.n
+    }
+    /// Check if the Map contains no entry
+    pub fn is_empty(&self) -> bool {
+        self.tree.n == 0
+    }
+    /// Insert a key/value pair, give back the old value if the key was already present
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let index = self.find(&key);
+        if index == NO_ENTRY {
+            self.tree.insert(MapEntry { key, value });
+            None
+        } else {
+            Some(core::mem::replace(
+                &mut self.tree.data[index].content_mut().value,
+                value,
+            ))
+        }
+    }
+    /// Get a reference to the value associated to the key
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + ?Sized,
+    {
+        let index = self.find(key);
+        (index != NO_ENTRY).then(|| &self.tree.data[index].content().value)
+    }
+    /// Get a mutable reference to the value associated to the key
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + ?Sized,
+    {
+        let index = self.find(key);
+        (index != NO_ENTRY).then(|| &mut self.tree.data[index].content_mut().value)
+    }
+    /// Check if the key is present into the Map
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + ?Sized,
+    {
+        self.find(key) != NO_ENTRY
+    }
+    /// Remove a key from the Map, give back its value
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + ?Sized,
+    {
+        let index = self.find(key);
+        (index != NO_ENTRY).then(|| self.tree.remove_index(index).value)
+    }
+    /// Create an iterator over the entries of the Map, ordered by key
+    pub fn iter(&self) -> RBBMapIterator<'_, K, V, A> {
+        RBBMapIterator {
+            inner: self.tree.iter(),
+        }
+    }
+    /// Create an iterator over the keys of the Map
+    pub fn keys(&self) -> RBBMapKeys<'_, K, V, A> {
+        RBBMapKeys {
+            inner: self.tree.iter(),
+        }
+    }
+    /// Create an iterator over the values of the Map, ordered by key
+    pub fn values(&self) -> RBBMapValues<'_, K, V, A> {
+        RBBMapValues {
+            inner: self.tree.iter(),
+        }
+    }
+    /// Check if the Map is okay
+    #[cfg(any(debug_assertions, test))]
+    pub fn check_nodes(&self) {
+        self.tree.check_nodes();
+    }
+    /// Fill an empty Map in O(n) as long as the keys are strictly increasing,
+    /// the following entries are inserted one by one
+    #[cfg(feature = "serde")]
+    pub(super) fn fill<I: Iterator<Item = (K, V)>>(&mut self, iter: I) {
+        debug_assert!(self.is_empty());
+        let mut iter = iter.map(|(key, value)| MapEntry { key, value });
+        if let Some(entry) = self.tree.extend_sorted_prefix(&mut iter, true) {
+            self.insert(entry.key, entry.value);
+            for entry in iter {
+                self.insert(entry.key, entry.value);
+            }
+        }
+    }
+    fn find<Q>(&self, key: &Q) -> usize
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + ?Sized,
+    {
+        self.tree
+            .find_by(|entry| key.partial_cmp(entry.key.borrow()))
+    }
+}
+
+impl<'a, K, V, A: Allocator> Iterator for RBBMapIterator<'a, K, V, A> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|entry| (&entry.key, &entry.value))
+    }
+}
+impl<'a, K, V, A: Allocator> DoubleEndedIterator for RBBMapIterator<'a, K, V, A> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner
+            .next_back()
+            .map(|entry| (&entry.key, &entry.value))
+    }
+}
+impl<'a, K, V, A: Allocator> Iterator for RBBMapKeys<'a, K, V, A> {
+    type Item = &'a K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|entry| &entry.key)
+    }
+}
+impl<'a, K, V, A: Allocator> DoubleEndedIterator for RBBMapKeys<'a, K, V, A> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(|entry| &entry.key)
+    }
+}
+impl<'a, K, V, A: Allocator> Iterator for RBBMapValues<'a, K, V, A> {
+    type Item = &'a V;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|entry| &entry.value)
+    }
+}
+impl<'a, K, V, A: Allocator> DoubleEndedIterator for RBBMapValues<'a, K, V, A> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(|entry| &entry.value)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::RBBMap;
+    use rand::prelude::*;
+    #[test]
+    fn insert_get_remove() {
+        let mut rng = rand::thread_rng();
+        let mut map = RBBMap::new();
+        let mut keys: Vec<u32> = (0..512).collect();
+        keys.shuffle(&mut rng);
+        for k in keys.iter() {
+            assert_eq!(map.insert(*k, k * 2), None);
+            map.check_nodes();
+        }
+        assert_eq!(map.len(), 512);
+        assert_eq!(map.insert(42, 0), Some(84));
+        *map.get_mut(&42).unwrap() = 84;
+        for k in keys.iter() {
+            assert_eq!(map.get(k), Some(&(k * 2)));
+        }
+        assert!(map.keys().copied().eq(0..512));
+        assert!(map.keys().rev().copied().eq((0..512).rev()));
+        assert!(map.values().copied().eq((0..512).map(|k| k * 2)));
+        assert_eq!(map.get(&1024), None);
+        keys.shuffle(&mut rng);
+        for k in keys.iter() {
+            assert_eq!(map.remove(k), Some(k * 2));
+            assert_eq!(map.remove(k), None);
+            map.check_nodes();
+        }
+        assert!(map.is_empty());
+        assert_eq!(map.iter().next(), None);
+    }
+}
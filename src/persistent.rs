@@ -0,0 +1,418 @@
+//! Persistent Red and Black Binary Tree
+//!
+//! Nodes are shared between versions through `Arc`, an update only copies the
+//! path going from the root to the modified node. The balancing cases are the
+//! same than the arena based tree, but without parent links the fix ups are done
+//! while going back up the recursion
+use super::Color;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use core::cmp::Ordering;
+
+type Link<T> = Option<Arc<PersistentNode<T>>>;
+
+#[derive(Clone)]
+struct PersistentNode<T> {
+    content: T,
+    color: Color,
+    left: Link<T>,
+    right: Link<T>,
+}
+
+/// Immutable Binary Tree, every update gives back a new version sharing the
+/// unchanged subtrees with the previous one. Cloning a version is O(1)
+pub struct PersistentRBBTree<T> {
+    root: Link<T>,
+    n: usize,
+}
+
+/// Iterator over a Persistent Binary Tree
+pub struct PersistentRBBTreeIterator<'a, T> {
+    stack: Vec<&'a PersistentNode<T>>,
+}
+
+impl<T: PartialOrd + Clone> PersistentRBBTree<T> {
+    /// Create a new empty Persistent Binary Tree
+    pub fn new() -> Self {
+        Self { root: None, n: 0 }
+    }
+    /// Number of elements into the Binary Tree
+    pub fn len(&self) -> usize {
+        self.n
+    }
+    /// Check if the Binary Tree contains no element
+    pub fn is_empty(&self) -> bool {
+        self.n == 0
+    }
+    /// Give back a new version containing `content`
+    pub fn insert(&self, content: T) -> Self {
+        let mut root = self.root.clone();
+        insert_recurse(&mut root, content);
+        node_mut(&mut root).color = Color::Black;
+        Self {
+            root,
+            n: self.n + 1,
+        }
+    }
+    /// Give back a new version without one occurence of `value`
+    pub fn remove<Q>(&self, value: &Q) -> Self
+    where
+        T: Borrow<Q>,
+        Q: PartialOrd + ?Sized,
+    {
+        if !self.contains(value) {
+            return self.clone();
+        }
+        let mut root = self.root.clone();
+        remove_recurse(&mut root, value);
+        if is_red(&root) {
+            node_mut(&mut root).color = Color::Black;
+        }
+        Self {
+            root,
+            n: self.n - 1,
+        }
+    }
+    /// Check if an element equal to `value` is present
+    pub fn contains<Q>(&self, value: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: PartialOrd + ?Sized,
+    {
+        self.get(value).is_some()
+    }
+    /// Get a reference to an element equal to `value`
+    pub fn get<Q>(&self, value: &Q) -> Option<&T>
+    where
+        T: Borrow<Q>,
+        Q: PartialOrd + ?Sized,
+    {
+        let mut link = &self.root;
+        while let Some(node) = link {
+            link = match value.partial_cmp(node.content.borrow()) {
+                Some(Ordering::Equal) => return Some(&node.content),
+                Some(Ordering::Less) => &node.left,
+                _ => &node.right,
+            };
+        }
+        None
+    }
+    /// Create an iterator over the Binary Tree
+    pub fn iter(&self) -> PersistentRBBTreeIterator<'_, T> {
+        let mut iter = PersistentRBBTreeIterator { stack: Vec::new() };
+        iter.push_left(&self.root);
+        iter
+    }
+    /// Check if the tree is okay
+    #[cfg(any(debug_assertions, test))]
+    pub fn check_nodes(&self) {
+        assert!(!is_red(&self.root));
+        let mut total_nodes = 0;
+        check_nodes_recurse(&self.root, Color::Black, &mut total_nodes);
+        assert_eq!(total_nodes, self.n);
+        assert!(self.iter().zip(self.iter().skip(1)).all(|(a, b)| a <= b));
+    }
+}
+
+impl<T> Clone for PersistentRBBTree<T> {
+    fn clone(&self) -> Self {
+        Self {
+            root: self.root.clone(),
+            n: self.n,
+        }
+    }
+}
+
+impl<T: PartialOrd + Clone> Default for PersistentRBBTree<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Mutable access to a node, it is copied first if another version shares it
+fn node_mut<T: Clone>(link: &mut Link<T>) -> &mut PersistentNode<T> {
+    Arc::make_mut(link.as_mut().expect("missing node"))
+}
+
+fn is_red<T>(link: &Link<T>) -> bool {
+    matches!(link, Some(node) if matches!(node.color, Color::Red))
+}
+
+/// Red node having a red child
+fn red_pair<T>(link: &Link<T>) -> bool {
+    match link {
+        Some(node) => {
+            matches!(node.color, Color::Red) && (is_red(&node.left) || is_red(&node.right))
+        }
+        None => false,
+    }
+}
+
+/// Rotate the node at `link` with its left child, the child takes its place
+fn rotate_right<T: Clone>(link: &mut Link<T>) {
+    let mut high = link.take();
+    let mut low = node_mut(&mut high).left.take();
+    node_mut(&mut high).left = node_mut(&mut low).right.take();
+    node_mut(&mut low).right = high;
+    *link = low;
+}
+
+/// Rotate the node at `link` with its right child, the child takes its place
+fn rotate_left<T: Clone>(link: &mut Link<T>) {
+    let mut high = link.take();
+    let mut low = node_mut(&mut high).right.take();
+    node_mut(&mut high).right = node_mut(&mut low).left.take();
+    node_mut(&mut low).left = high;
+    *link = low;
+}
+
+fn insert_recurse<T: PartialOrd + Clone>(link: &mut Link<T>, content: T) {
+    let Some(node) = link else {
+        *link = Some(Arc::new(PersistentNode {
+            content,
+            color: Color::Red,
+            left: None,
+            right: None,
+        }));
+        return;
+    };
+    let node = Arc::make_mut(node);
+    if content < node.content {
+        insert_recurse(&mut node.left, content);
+    } else {
+        insert_recurse(&mut node.right, content);
+    }
+    insert_fix(link);
+}
+
+/// Fix a red node having a red child below the grandparent at `link`, same
+/// cases than `insert_recurse`
+fn insert_fix<T: Clone>(link: &mut Link<T>) {
+    let pp = node_mut(link);
+    let p_left = if red_pair(&pp.left) {
+        true
+    } else if red_pair(&pp.right) {
+        false
+    } else {
+        return;
+    };
+    let (p, f) = if p_left {
+        (&mut pp.left, &mut pp.right)
+    } else {
+        (&mut pp.right, &mut pp.left)
+    };
+    if is_red(f) {
+        // The red goes up to the grandparent
+        node_mut(f).color = Color::Black;
+        node_mut(p).color = Color::Black;
+        pp.color = Color::Red;
+        return;
+    }
+    if p_left {
+        if !is_red(&node_mut(p).left) {
+            rotate_left(p);
+        }
+        rotate_right(link);
+        let top = node_mut(link);
+        top.color = Color::Black;
+        node_mut(&mut top.right).color = Color::Red;
+    } else {
+        if !is_red(&node_mut(p).right) {
+            rotate_right(p);
+        }
+        rotate_left(link);
+        let top = node_mut(link);
+        top.color = Color::Black;
+        node_mut(&mut top.left).color = Color::Red;
+    }
+}
+
+/// Remove one node equal to `value` from the subtree at `link`, give back its
+/// content and whether the subtree lost a black level. Same cases than
+/// `remove_find_case`
+fn remove_recurse<T, Q>(link: &mut Link<T>, value: &Q) -> (T, bool)
+where
+    T: PartialOrd + Clone + Borrow<Q>,
+    Q: PartialOrd + ?Sized,
+{
+    let node = node_mut(link);
+    let (content, shorter, left) = match value.partial_cmp(node.content.borrow()) {
+        Some(Ordering::Equal) if node.left.is_some() && node.right.is_some() => {
+            // Take the place of the leftmost node of the right subtree
+            let (next, shorter) = remove_first(&mut node.right);
+            (core::mem::replace(&mut node.content, next), shorter, false)
+        }
+        Some(Ordering::Equal) => return unlink(link),
+        Some(Ordering::Less) => {
+            let (content, shorter) = remove_recurse(&mut node.left, value);
+            (content, shorter, true)
+        }
+        _ => {
+            let (content, shorter) = remove_recurse(&mut node.right, value);
+            (content, shorter, false)
+        }
+    };
+    (content, shorter && remove_fix(link, left))
+}
+
+fn remove_first<T: Clone>(link: &mut Link<T>) -> (T, bool) {
+    let node = node_mut(link);
+    if node.left.is_none() {
+        return unlink(link);
+    }
+    let (content, shorter) = remove_first(&mut node.left);
+    (content, shorter && remove_fix(link, true))
+}
+
+/// Replace a node having at most one child by this child
+fn unlink<T: Clone>(link: &mut Link<T>) -> (T, bool) {
+    let node = link.take().expect("missing node");
+    let node = Arc::try_unwrap(node).unwrap_or_else(|node| (*node).clone());
+    *link = node.left.or(node.right);
+    let shorter = if matches!(node.color, Color::Red) {
+        false
+    } else if is_red(link) {
+        node_mut(link).color = Color::Black;
+        false
+    } else {
+        true
+    };
+    (node.content, shorter)
+}
+
+/// Rebalance the node at `link` after one of its subtrees lost a black level,
+/// same cases than `remove_modify_tree`. Give back true if the whole subtree is
+/// still one black level short
+fn remove_fix<T: Clone>(link: &mut Link<T>, left_shorter: bool) -> bool {
+    let p = node_mut(link);
+    let p_color = p.color;
+    let f_link = if left_shorter {
+        &mut p.right
+    } else {
+        &mut p.left
+    };
+    let f = node_mut(f_link);
+    let (sl_red, sr_red) = (is_red(&f.left), is_red(&f.right));
+    match (left_shorter, f.color, sl_red, sr_red) {
+        (_, Color::Black, false, false) => {
+            f.color = Color::Red;
+            if matches!(p.color, Color::Red) {
+                p.color = Color::Black;
+                false
+            } else {
+                true
+            }
+        }
+        (false, Color::Black, true, _) => {
+            f.color = p_color;
+            node_mut(&mut f.left).color = Color::Black;
+            p.color = Color::Black;
+            rotate_right(link);
+            false
+        }
+        (true, Color::Black, _, true) => {
+            f.color = p_color;
+            node_mut(&mut f.right).color = Color::Black;
+            p.color = Color::Black;
+            rotate_left(link);
+            false
+        }
+        (false, Color::Black, _, true) => {
+            node_mut(&mut f.right).color = p_color;
+            p.color = Color::Black;
+            rotate_left(f_link);
+            rotate_right(link);
+            false
+        }
+        (true, Color::Black, true, _) => {
+            node_mut(&mut f.left).color = p_color;
+            p.color = Color::Black;
+            rotate_right(f_link);
+            rotate_left(link);
+            false
+        }
+        (false, Color::Red, _, _) => {
+            rotate_right(link);
+            let top = node_mut(link);
+            top.color = Color::Black;
+            node_mut(&mut top.right).color = Color::Red;
+            remove_fix(&mut top.right, false)
+        }
+        (true, Color::Red, _, _) => {
+            rotate_left(link);
+            let top = node_mut(link);
+            top.color = Color::Black;
+            node_mut(&mut top.left).color = Color::Red;
+            remove_fix(&mut top.left, true)
+        }
+    }
+}
+
+/// Check the subtree, give back its black height
+#[cfg(any(debug_assertions, test))]
+fn check_nodes_recurse<T>(link: &Link<T>, color: Color, acc: &mut usize) -> usize {
+    let Some(node) = link else {
+        return 0;
+    };
+    if matches!(node.color, Color::Red) && color as u64 == Color::Red as u64 {
+        panic!("A red node follow a red node: bl_lvl {}", acc);
+    }
+    *acc += 1;
+    let black_left = check_nodes_recurse(&node.left, node.color, acc);
+    let black_right = check_nodes_recurse(&node.right, node.color, acc);
+    assert_eq!(black_left, black_right);
+    black_left + matches!(node.color, Color::Black) as usize
+}
+
+impl<'a, T> PersistentRBBTreeIterator<'a, T> {
+    fn push_left(&mut self, mut link: &'a Link<T>) {
+        while let Some(node) = link {
+            self.stack.push(node);
+            link = &node.left;
+        }
+    }
+}
+
+impl<'a, T> Iterator for PersistentRBBTreeIterator<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.stack.pop()?;
+        self.push_left(&node.right);
+        Some(&node.content)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::PersistentRBBTree;
+    use rand::prelude::*;
+    #[test]
+    fn versions() {
+        let mut rng = rand::thread_rng();
+        let mut versions = vec![(PersistentRBBTree::new(), Vec::new())];
+        for _i in 0..512 {
+            let (tree, v) = versions.choose(&mut rng).unwrap();
+            let (tree, mut v) = (tree.clone(), v.clone());
+            let val: u32 = rng.gen_range(0..128);
+            let tree = if rng.gen_bool(0.6) {
+                v.push(val);
+                v.sort();
+                tree.insert(val)
+            } else {
+                if let Some(pos) = v.iter().position(|x| *x == val) {
+                    v.remove(pos);
+                }
+                tree.remove(&val)
+            };
+            tree.check_nodes();
+            versions.push((tree, v));
+        }
+        for (tree, v) in versions.iter() {
+            tree.check_nodes();
+            assert_eq!(tree.len(), v.len());
+            assert!(tree.iter().eq(v.iter()));
+        }
+    }
+}
@@ -0,0 +1,122 @@
+//! Serde support, trees are serialized as sorted sequences and maps as sorted maps
+//!
+//! Sorted inputs are rebuilt in O(n), the elements breaking the order are
+//! inserted one by one
+use super::{Allocator, Augment, Global, RBBMap, RBBTree};
+use core::fmt;
+use core::marker::PhantomData;
+use serde::de::{Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::ser::{Serialize, Serializer};
+
+impl<T: PartialOrd + Serialize, A: Allocator, G: Augment<T>> Serialize for RBBTree<T, A, G> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+impl<K: PartialOrd + Serialize, V: Serialize, A: Allocator> Serialize for RBBMap<K, V, A> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.iter())
+    }
+}
+
+struct TreeVisitor<T, G> {
+    phantom: PhantomData<fn() -> (T, G)>,
+}
+
+impl<'de, T: PartialOrd + Deserialize<'de>, G: Augment<T>> Visitor<'de> for TreeVisitor<T, G> {
+    type Value = RBBTree<T, Global, G>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a sequence")
+    }
+
+    fn visit_seq<S: SeqAccess<'de>>(self, mut seq: S) -> Result<Self::Value, S::Error> {
+        let mut tree = RBBTree::default();
+        let mut error = None;
+        tree.extend_sorted(core::iter::from_fn(|| {
+            seq.next_element().unwrap_or_else(|e| {
+                error = Some(e);
+                None
+            })
+        }));
+        match error {
+            Some(e) => Err(e),
+            None => Ok(tree),
+        }
+    }
+}
+
+impl<'de, T: PartialOrd + Deserialize<'de>, G: Augment<T>> Deserialize<'de>
+    for RBBTree<T, Global, G>
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_seq(TreeVisitor {
+            phantom: PhantomData,
+        })
+    }
+}
+
+struct MapVisitor<K, V> {
+    phantom: PhantomData<fn() -> (K, V)>,
+}
+
+impl<'de, K: PartialOrd + Deserialize<'de>, V: Deserialize<'de>> Visitor<'de> for MapVisitor<K, V> {
+    type Value = RBBMap<K, V>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a map")
+    }
+
+    /// The last value wins when a key is repeated
+    fn visit_map<M: MapAccess<'de>>(self, mut access: M) -> Result<Self::Value, M::Error> {
+        let mut map = RBBMap::new();
+        let mut error = None;
+        map.fill(core::iter::from_fn(|| {
+            access.next_entry().unwrap_or_else(|e| {
+                error = Some(e);
+                None
+            })
+        }));
+        match error {
+            Some(e) => Err(e),
+            None => Ok(map),
+        }
+    }
+}
+
+impl<'de, K: PartialOrd + Deserialize<'de>, V: Deserialize<'de>> Deserialize<'de> for RBBMap<K, V> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_map(MapVisitor {
+            phantom: PhantomData,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{RBBMap, RBBTree};
+    #[test]
+    fn round_trip() {
+        let tree: RBBTree<u32> = (0..100).map(|x| x * 7 % 31).collect();
+        let json = serde_json::to_string(&tree).unwrap();
+        let mut expected: Vec<u32> = tree.iter().copied().collect();
+        assert_eq!(json, serde_json::to_string(&expected).unwrap());
+        let tree: RBBTree<u32> = serde_json::from_str(&json).unwrap();
+        tree.check_nodes();
+        assert!(tree.iter().eq(expected.iter()));
+        let tree: RBBTree<u32> = serde_json::from_str("[1, 2, 5, 3, 3, 0]").unwrap();
+        tree.check_nodes();
+        expected = vec![0, 1, 2, 3, 3, 5];
+        assert!(tree.iter().eq(expected.iter()));
+        assert!(serde_json::from_str::<RBBTree<u32>>("[1, -2]").is_err());
+
+        let map: RBBMap<String, u32> =
+            serde_json::from_str(r#"{"a": 1, "b": 2, "d": 3, "c": 4, "a": 5}"#).unwrap();
+        map.check_nodes();
+        assert_eq!(
+            serde_json::to_string(&map).unwrap(),
+            r#"{"a":5,"b":2,"c":4,"d":3}"#
+        );
+    }
+}
@@ -0,0 +1,176 @@
+//! Set algebra over two Red and Black Binary Trees
+//!
+//! Both trees are walked in order at the same time, so every operation costs
+//! O(n + m). Trees are multisets: an element present `i` times into `self` and
+//! `j` times into `other` is yielded `max(i, j)` times by the union, `min(i, j)`
+//! times by the intersection, `i - j` times by the difference and `|i - j|`
+//! times by the symmetric difference.
+use super::{Allocator, Augment, Global, RBBTree, RBBTreeIterator};
+use core::iter::Peekable;
+
+/// Lazy union of two Binary Trees
+pub struct RBBTreeUnion<'a, T, A: Allocator = Global, S = ()> {
+    merge: Merge<'a, T, A, S>,
+}
+
+/// Lazy intersection of two Binary Trees
+pub struct RBBTreeIntersection<'a, T, A: Allocator = Global, S = ()> {
+    merge: Merge<'a, T, A, S>,
+}
+
+/// Lazy difference of two Binary Trees
+pub struct RBBTreeDifference<'a, T, A: Allocator = Global, S = ()> {
+    merge: Merge<'a, T, A, S>,
+}
+
+/// Lazy symmetric difference of two Binary Trees
+pub struct RBBTreeSymmetricDifference<'a, T, A: Allocator = Global, S = ()> {
+    merge: Merge<'a, T, A, S>,
+}
+
+/// Walk two sorted iterators at the same time
+struct Merge<'a, T, A: Allocator, S> {
+    a: Peekable<RBBTreeIterator<'a, T, A, S>>,
+    b: Peekable<RBBTreeIterator<'a, T, A, S>>,
+}
+
+impl<'a, T: PartialOrd, A: Allocator, S> Merge<'a, T, A, S> {
+    /// Consume the lowest element, or both elements when they are equal
+    fn next_pair(&mut self) -> (Option<&'a T>, Option<&'a T>) {
+        match (self.a.peek(), self.b.peek()) {
+            (Some(a), Some(b)) if a < b => (self.a.next(), None),
+            (Some(a), Some(b)) if b < a => (None, self.b.next()),
+            (Some(_), Some(_)) => (self.a.next(), self.b.next()),
+            (Some(_), None) => (self.a.next(), None),
+            (None, Some(_)) => (None, self.b.next()),
+            (None, None) => (None, None),
+        }
+    }
+}
+
+impl<T: PartialOrd, A: Allocator, G: Augment<T>> RBBTree<T, A, G> {
+    /// Create an iterator over the elements of `self` or `other`
+    pub fn union<'a>(&'a self, other: &'a Self) -> RBBTreeUnion<'a, T, A, G::Value> {
+        RBBTreeUnion {
+            merge: self.merge(other),
+        }
+    }
+    /// Create an iterator over the elements of both `self` and `other`
+    pub fn intersection<'a>(&'a self, other: &'a Self) -> RBBTreeIntersection<'a, T, A, G::Value> {
+        RBBTreeIntersection {
+            merge: self.merge(other),
+        }
+    }
+    /// Create an iterator over the elements of `self` missing into `other`
+    pub fn difference<'a>(&'a self, other: &'a Self) -> RBBTreeDifference<'a, T, A, G::Value> {
+        RBBTreeDifference {
+            merge: self.merge(other),
+        }
+    }
+    /// Create an iterator over the elements present into only one tree
+    pub fn symmetric_difference<'a>(
+        &'a self,
+        other: &'a Self,
+    ) -> RBBTreeSymmetricDifference<'a, T, A, G::Value> {
+        RBBTreeSymmetricDifference {
+            merge: self.merge(other),
+        }
+    }
+    /// Check if all the elements of `self` are present into `other`
+    pub fn is_subset(&self, other: &Self) -> bool {
+        self.difference(other).next().is_none()
+    }
+    /// Check if all the elements of `other` are present into `self`
+    pub fn is_superset(&self, other: &Self) -> bool {
+        other.is_subset(self)
+    }
+    /// Check if `self` and `other` have no element in common
+    pub fn is_disjoint(&self, other: &Self) -> bool {
+        self.intersection(other).next().is_none()
+    }
+    fn merge<'a>(&'a self, other: &'a Self) -> Merge<'a, T, A, G::Value> {
+        Merge {
+            a: self.iter().peekable(),
+            b: other.iter().peekable(),
+        }
+    }
+}
+
+impl<'a, T: PartialOrd, A: Allocator, S> Iterator for RBBTreeUnion<'a, T, A, S> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.merge.next_pair() {
+            (Some(a), _) => Some(a),
+            (None, b) => b,
+        }
+    }
+}
+
+impl<'a, T: PartialOrd, A: Allocator, S> Iterator for RBBTreeIntersection<'a, T, A, S> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.merge.next_pair() {
+                (Some(a), Some(_)) => return Some(a),
+                (None, None) => return None,
+                _ => {}
+            }
+        }
+    }
+}
+
+impl<'a, T: PartialOrd, A: Allocator, S> Iterator for RBBTreeDifference<'a, T, A, S> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.merge.next_pair() {
+                (Some(a), None) => return Some(a),
+                (None, None) => return None,
+                _ => {}
+            }
+        }
+    }
+}
+
+impl<'a, T: PartialOrd, A: Allocator, S> Iterator for RBBTreeSymmetricDifference<'a, T, A, S> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.merge.next_pair() {
+                (Some(a), None) => return Some(a),
+                (None, Some(b)) => return Some(b),
+                (None, None) => return None,
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::RBBTree;
+    #[test]
+    fn set_algebra() {
+        let a = RBBTree::from([1, 2, 2, 3, 5, 8, 8, 8]);
+        let b = RBBTree::from([2, 3, 4, 8, 9]);
+        assert!(a.union(&b).copied().eq([1, 2, 2, 3, 4, 5, 8, 8, 8, 9]));
+        assert!(a.intersection(&b).copied().eq([2, 3, 8]));
+        assert!(a.difference(&b).copied().eq([1, 2, 5, 8, 8]));
+        assert!(b.difference(&a).copied().eq([4, 9]));
+        assert!(a
+            .symmetric_difference(&b)
+            .copied()
+            .eq([1, 2, 4, 5, 8, 8, 9]));
+        let c = RBBTree::from([2, 8, 8]);
+        assert!(c.is_subset(&a));
+        assert!(a.is_superset(&c));
+        assert!(!c.is_subset(&b));
+        assert!(!a.is_disjoint(&b));
+        assert!(RBBTree::from([0, 6, 7]).is_disjoint(&a));
+        assert!(RBBTree::new().is_subset(&a));
+    }
+}
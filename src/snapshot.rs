@@ -0,0 +1,337 @@
+//! Binary snapshots of the Red and Black Binary Tree
+//!
+//! The arena is dumped as is, so a snapshot is reloaded without any rebalancing.
+//! Links and colors are validated on load and a corrupted snapshot gives back an
+//! error. Subtree sizes and summaries are not stored, they are recomputed
+use super::{Allocator, Augment, Color, Global, Node, RBBTree, NO_ENTRY};
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+const MAGIC: &[u8; 4] = b"RBBT";
+const VERSION: u32 = 1;
+
+/// Binary encoding of the elements stored into a snapshot
+pub trait Encode: Sized {
+    /// Append the element to `out`
+    fn encode(&self, out: &mut Vec<u8>);
+    /// Read an element from the head of `input` and advance it
+    fn decode(input: &mut &[u8]) -> Result<Self, SnapshotError>;
+}
+
+/// Reasons for a snapshot to be refused
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SnapshotError {
+    /// The data does not start with the snapshot magic number
+    BadMagic,
+    /// The snapshot was written by an unknown version of the format
+    UnsupportedVersion(u32),
+    /// The data ends in the middle of the snapshot
+    UnexpectedEnd,
+    /// An element could not be decoded
+    InvalidContent,
+    /// The nodes do not form a valid Red and Black Binary Tree
+    Corrupted(&'static str),
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BadMagic => write!(f, "not a tree snapshot"),
+            Self::UnsupportedVersion(v) => write!(f, "unsupported snapshot version {}", v),
+            Self::UnexpectedEnd => write!(f, "truncated snapshot"),
+            Self::InvalidContent => write!(f, "invalid element in snapshot"),
+            Self::Corrupted(reason) => write!(f, "corrupted snapshot: {}", reason),
+        }
+    }
+}
+
+impl core::error::Error for SnapshotError {}
+
+/// Split the `len` first bytes of `input`
+fn take<'a>(input: &mut &'a [u8], len: usize) -> Result<&'a [u8], SnapshotError> {
+    if input.len() < len {
+        return Err(SnapshotError::UnexpectedEnd);
+    }
+    let (head, tail) = input.split_at(len);
+    *input = tail;
+    Ok(head)
+}
+
+macro_rules! encode_int {
+    ($($t:ty),*) => {$(
+        impl Encode for $t {
+            fn encode(&self, out: &mut Vec<u8>) {
+                out.extend_from_slice(&self.to_le_bytes());
+            }
+            fn decode(input: &mut &[u8]) -> Result<Self, SnapshotError> {
+                let bytes = take(input, core::mem::size_of::<$t>())?;
+                Ok(<$t>::from_le_bytes(bytes.try_into().unwrap()))
+            }
+        }
+    )*};
+}
+encode_int!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128);
+
+impl Encode for bool {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(*self as u8);
+    }
+    fn decode(input: &mut &[u8]) -> Result<Self, SnapshotError> {
+        match u8::decode(input)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(SnapshotError::InvalidContent),
+        }
+    }
+}
+
+impl Encode for String {
+    fn encode(&self, out: &mut Vec<u8>) {
+        (self.len() as u64).encode(out);
+        out.extend_from_slice(self.as_bytes());
+    }
+    fn decode(input: &mut &[u8]) -> Result<Self, SnapshotError> {
+        let len = decode_usize(input)?;
+        let bytes = take(input, len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| SnapshotError::InvalidContent)
+    }
+}
+
+fn decode_usize(input: &mut &[u8]) -> Result<usize, SnapshotError> {
+    usize::try_from(u64::decode(input)?).map_err(|_| SnapshotError::Corrupted("size overflow"))
+}
+
+/// Links are stored on 64 bits, `NO_ENTRY` as `u64::MAX`
+fn encode_link(x: usize, out: &mut Vec<u8>) {
+    match x {
+        NO_ENTRY => u64::MAX.encode(out),
+        x => (x as u64).encode(out),
+    }
+}
+
+/// Links must point into the `n` nodes of the arena
+fn decode_link(input: &mut &[u8], n: usize) -> Result<usize, SnapshotError> {
+    match u64::decode(input)? {
+        u64::MAX => Ok(NO_ENTRY),
+        x if x < n as u64 => Ok(x as usize),
+        _ => Err(SnapshotError::Corrupted("link out of range")),
+    }
+}
+
+impl<T: PartialOrd + Encode, A: Allocator, G: Augment<T>> RBBTree<T, A, G> {
+    /// Dump the arena, the root and the number of nodes into a binary snapshot
+    pub fn to_snapshot(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(MAGIC);
+        VERSION.encode(&mut out);
+        (self.n as u64).encode(&mut out);
+        // The free slots of a free-list arena are skipped, links are renumbered
+        #[cfg(feature = "free-list")]
+        let rank: Vec<usize> = self
+            .data
+            .iter()
+            .scan(0, |live, node| {
+                let rank = *live;
+                *live += !node.is_free() as usize;
+                Some(rank)
+            })
+            .collect();
+        let link = |x: usize| {
+            #[cfg(feature = "free-list")]
+            if x != NO_ENTRY {
+                return rank[x];
+            }
+            x
+        };
+        encode_link(link(self.root.unwrap_or(NO_ENTRY)), &mut out);
+        for node in self.data.iter() {
+            #[cfg(feature = "free-list")]
+            if node.is_free() {
+                continue;
+            }
+            node.content().encode(&mut out);
+            (node.color() as u8).encode(&mut out);
+            encode_link(link(node.parent()), &mut out);
+            encode_link(link(node.left()), &mut out);
+            encode_link(link(node.right()), &mut out);
+        }
+        out
+    }
+}
+
+impl<T: PartialOrd + Encode, G: Augment<T>> RBBTree<T, Global, G> {
+    /// Reload a tree from a binary snapshot without rebalancing it. Every node
+    /// is checked, a corrupted snapshot gives back an error
+    pub fn from_snapshot(mut input: &[u8]) -> Result<Self, SnapshotError> {
+        let input = &mut input;
+        if take(input, MAGIC.len())? != MAGIC {
+            return Err(SnapshotError::BadMagic);
+        }
+        let version = u32::decode(input)?;
+        if version != VERSION {
+            return Err(SnapshotError::UnsupportedVersion(version));
+        }
+        let n = decode_usize(input)?;
+        #[cfg(feature = "compact")]
+        if n >= super::NIL as usize {
+            return Err(SnapshotError::Corrupted("too many nodes for compact links"));
+        }
+        let root = decode_link(input, n)?;
+        let mut tree = Self::default();
+        for _i in 0..n {
+            let mut node = Node::new(T::decode(input)?, G::identity());
+            node.set_color(match u8::decode(input)? {
+                0 => Color::Red,
+                1 => Color::Black,
+                _ => return Err(SnapshotError::Corrupted("unknown color")),
+            });
+            node.set_parent(decode_link(input, n)?);
+            node.set_left(decode_link(input, n)?);
+            node.set_right(decode_link(input, n)?);
+            tree.data.push(node);
+        }
+        if !input.is_empty() {
+            return Err(SnapshotError::Corrupted("trailing bytes"));
+        }
+        tree.n = n;
+        tree.root = (root != NO_ENTRY).then_some(root);
+        tree.validate()?;
+        Ok(tree)
+    }
+}
+
+impl<T: PartialOrd, A: Allocator, G: Augment<T>> RBBTree<T, A, G> {
+    /// Same checks than `check_nodes` without panicking, the subtree sizes and
+    /// summaries are recomputed on the way
+    fn validate(&mut self) -> Result<(), SnapshotError> {
+        let Some(root) = self.root else {
+            return match self.n {
+                0 => Ok(()),
+                _ => Err(SnapshotError::Corrupted("nodes without root")),
+            };
+        };
+        if root >= self.data.len() {
+            return Err(SnapshotError::Corrupted("link out of range"));
+        }
+        if self.data[root].parent() != NO_ENTRY {
+            return Err(SnapshotError::Corrupted("root has a parent"));
+        }
+        if is_red!(self.data[root]) {
+            return Err(SnapshotError::Corrupted("red root"));
+        }
+        // A Red and Black Binary Tree is never deeper than 2 * log2(n + 1)
+        let max_depth = 2 * (usize::BITS - self.n.leading_zeros());
+        let (mut count, mut prev) = (0, NO_ENTRY);
+        self.validate_recurse(root, 1, max_depth, &mut count, &mut prev)?;
+        if count != self.n {
+            return Err(SnapshotError::Corrupted("unreachable nodes"));
+        }
+        Ok(())
+    }
+    /// Check the subtree `x` in order, give back its black height
+    fn validate_recurse(
+        &mut self,
+        x: usize,
+        depth: u32,
+        max_depth: u32,
+        count: &mut usize,
+        prev: &mut usize,
+    ) -> Result<usize, SnapshotError> {
+        if depth > max_depth {
+            return Err(SnapshotError::Corrupted("tree too deep"));
+        }
+        let (left, right) = (self.data[x].left(), self.data[x].right());
+        if left == right && left != NO_ENTRY {
+            return Err(SnapshotError::Corrupted("node linked twice"));
+        }
+        for child in [left, right] {
+            if child == NO_ENTRY {
+                continue;
+            }
+            if child >= self.data.len() {
+                return Err(SnapshotError::Corrupted("link out of range"));
+            }
+            if self.data[child].parent() != x {
+                return Err(SnapshotError::Corrupted("orphan node"));
+            }
+            if is_red!(self.data[x]) && is_red!(self.data[child]) {
+                return Err(SnapshotError::Corrupted("a red node follows a red node"));
+            }
+        }
+        let black_left = match left {
+            NO_ENTRY => 0,
+            l => self.validate_recurse(l, depth + 1, max_depth, count, prev)?,
+        };
+        if *prev != NO_ENTRY && *self.data[x].content() < *self.data[*prev].content() {
+            return Err(SnapshotError::Corrupted("unordered nodes"));
+        }
+        *prev = x;
+        *count += 1;
+        let black_right = match right {
+            NO_ENTRY => 0,
+            r => self.validate_recurse(r, depth + 1, max_depth, count, prev)?,
+        };
+        if black_left != black_right {
+            return Err(SnapshotError::Corrupted("black heights differ"));
+        }
+        self.update_node(x);
+        if is_black!(self.data[x]) {
+            Ok(black_left + 1)
+        } else {
+            Ok(black_left)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::SnapshotError;
+    use crate::RBBTree;
+    use rand::prelude::*;
+    #[test]
+    fn round_trip() {
+        let mut rng = rand::thread_rng();
+        let mut rnb = RBBTree::new();
+        for _i in 0..256 {
+            rnb.insert(rng.gen_range(0..1024u32));
+        }
+        for _i in 0..64 {
+            rnb.remove(&rng.gen_range(0..1024u32));
+        }
+        let loaded = RBBTree::<u32>::from_snapshot(&rnb.to_snapshot()).unwrap();
+        loaded.check_nodes();
+        assert!(loaded.iter().eq(rnb.iter()));
+        let empty = RBBTree::<String>::new().to_snapshot();
+        assert_eq!(
+            RBBTree::<String>::from_snapshot(&empty)
+                .unwrap()
+                .iter()
+                .next(),
+            None
+        );
+    }
+    #[test]
+    fn corruption() {
+        let rnb: RBBTree<u8> = (0..16).collect();
+        let snapshot = rnb.to_snapshot();
+        assert_eq!(
+            RBBTree::<u8>::from_snapshot(&snapshot[1..]).err(),
+            Some(SnapshotError::BadMagic)
+        );
+        assert_eq!(
+            RBBTree::<u8>::from_snapshot(&snapshot[..snapshot.len() - 1]).err(),
+            Some(SnapshotError::UnexpectedEnd)
+        );
+        // Any flipped byte is either refused or gives back a valid tree
+        for i in 0..snapshot.len() {
+            for flip in [1, 0x80, 0xff] {
+                let mut corrupted = snapshot.clone();
+                corrupted[i] ^= flip;
+                if let Ok(loaded) = RBBTree::<u8>::from_snapshot(&corrupted) {
+                    loaded.check_nodes();
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,175 @@
+//! Set of unique elements based on the Red and Black Binary Tree
+use super::{Allocator, Global, RBBTree, RBBTreeIterator, NO_ENTRY};
+use core::borrow::Borrow;
+
+/// Set Structure, an element equal to a stored one is never inserted twice
+pub struct RBBSet<T: PartialOrd, A: Allocator = Global> {
+    tree: RBBTree<T, A>,
+}
+
+impl<T: PartialOrd> RBBSet<T> {
+    /// Create a new Set
+    pub fn new() -> Self {
+        Self {
+            tree: RBBTree::new(),
+        }
+    }
+}
+impl<T: PartialOrd> Default for RBBSet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl<T: PartialOrd, A: Allocator> RBBSet<T, A> {
+    #[cfg(nightly)]
+    /// Create a new Set with Custom Allocator
+    pub fn new_in(alloc: A) -> Self {
+        Self {
+            tree: RBBTree::new_in(alloc),
+        }
+    }
+    /// Number of elements into the Set
+    pub fn len(&self) -> usize {
+        self.tree.n
+    }
+    /// Check if the Set contains no element
+    pub fn is_empty(&self) -> bool {
+        self.tree.n == 0
+    }
+    /// Insert an element, give back `false` and drop it if an equal element
+    /// was already present
+    pub fn insert(&mut self, content: T) -> bool {
+        if self.tree.find_first(&content) != NO_ENTRY {
+            return false;
+        }
+        self.tree.insert(content);
+        true
+    }
+    /// Insert an element, replacing and giving back an equal element if one
+    /// was already present
+    pub fn replace(&mut self, content: T) -> Option<T> {
+        self.tree.replace(content)
+    }
+    /// Check if an element is present into the Set
+    pub fn contains<Q>(&self, value: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: PartialOrd + ?Sized,
+    {
+        self.tree.contains(value)
+    }
+    /// Get a reference to the stored element equal to the value
+    pub fn get<Q>(&self, value: &Q) -> Option<&T>
+    where
+        T: Borrow<Q>,
+        Q: PartialOrd + ?Sized,
+    {
+        self.tree.get(value)
+    }
+    /// Remove the element equal to the value
+    pub fn remove<Q>(&mut self, value: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: PartialOrd + ?Sized,
+    {
+        self.tree.remove(value)
+    }
+    /// Remove the element equal to the value and give it back
+    pub fn take<Q>(&mut self, value: &Q) -> Option<T>
+    where
+        T: Borrow<Q>,
+        Q: PartialOrd + ?Sized,
+    {
+        self.tree.take(value)
+    }
+    /// Get a reference to the smallest element
+    pub fn first(&self) -> Option<&T> {
+        self.tree.first()
+    }
+    /// Get a reference to the greatest element
+    pub fn last(&self) -> Option<&T> {
+        self.tree.last()
+    }
+    /// Remove the smallest element and give it back
+    pub fn pop_first(&mut self) -> Option<T> {
+        self.tree.pop_first()
+    }
+    /// Remove the greatest element and give it back
+    pub fn pop_last(&mut self) -> Option<T> {
+        self.tree.pop_last()
+    }
+    /// Create an iterator over the Set
+    pub fn iter(&self) -> RBBTreeIterator<'_, T, A> {
+        self.tree.iter()
+    }
+    /// Read only access to the underlying tree, for ranges, ranks and set algebra
+    pub fn as_tree(&self) -> &RBBTree<T, A> {
+        &self.tree
+    }
+    /// Check if the Set is okay
+    #[cfg(any(debug_assertions, test))]
+    pub fn check_nodes(&self) {
+        self.tree.check_nodes();
+        assert!(self
+            .tree
+            .iter()
+            .zip(self.tree.iter().skip(1))
+            .all(|(a, b)| a < b));
+    }
+}
+
+impl<T: PartialOrd, A: Allocator> Extend<T> for RBBSet<T, A> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for content in iter {
+            self.insert(content);
+        }
+    }
+}
+
+impl<T: PartialOrd> FromIterator<T> for RBBSet<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut set = Self::new();
+        set.extend(iter);
+        set
+    }
+}
+
+impl<'a, T: PartialOrd, A: Allocator> IntoIterator for &'a RBBSet<T, A> {
+    type Item = &'a T;
+    type IntoIter = RBBTreeIterator<'a, T, A>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::RBBSet;
+    use rand::prelude::*;
+    #[test]
+    fn unique() {
+        let mut rng = rand::thread_rng();
+        let mut set = RBBSet::new();
+        let mut expected = std::collections::BTreeSet::new();
+        for _i in 0..512 {
+            let val: u32 = rng.gen_range(0..128);
+            assert_eq!(set.insert(val), expected.insert(val));
+        }
+        set.check_nodes();
+        assert_eq!(set.len(), expected.len());
+        assert!(set.iter().eq(expected.iter()));
+        for val in 0..64 {
+            assert_eq!(set.remove(&val), expected.remove(&val));
+            assert!(!set.contains(&val));
+        }
+        set.check_nodes();
+        assert!(set.iter().eq(expected.iter()));
+        let other: RBBSet<u32> = (32..96).chain(32..96).collect();
+        assert_eq!(other.len(), 64);
+        assert!(set
+            .as_tree()
+            .intersection(other.as_tree())
+            .eq(expected.range(64..96)));
+    }
+}